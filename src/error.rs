@@ -11,14 +11,36 @@ pub enum SqpackError {
     SqFileNotFound,
     /// An IO Error occurred
     IO(IOError),
+    /// The reader does not start with a SqPack header
+    NotSqPack,
     /// The IndexReader was not initialized over an index file
     IndexReaderIsNotIndex,
+    /// The DatReader was not initialized over a .dat file
+    DatReaderIsNotDat,
     /// The content type read from a .dat file was unknown.
     /// This usually means a SqFile was attempted to be initialized
-    /// over the incorrect .dat file reader. If the value contained
-    /// is 1, 3, or 4 however, it is valid, but we do not support those
-    /// ContentTypes yet.
+    /// over the incorrect .dat file reader.
     UnknownContentType(u32),
+    /// The platform ID read from a SqPack header was unknown, so its byte order is unknown.
+    UnknownPlatform(u8),
+    /// A SqPath could not be parsed to find the index file it belongs to
+    InvalidSqPath(SqPathError),
+    /// A SqPath added to a SqPackWriter belongs to a different index file than the paths added
+    /// before it
+    MismatchedIndexFile(String),
+}
+
+/// The part of a SqPath that prevented it from being parsed
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum SqPathError {
+    /// The path has no `/`, so it has neither a category nor a folder
+    NoFolder,
+    /// The path ends with a `/`, so it has no file name
+    NoFileName,
+    /// The first segment of the path is not a known category
+    UnknownCategory(String),
+    /// The path is stored in a category split into chunks, but does not name its chunk
+    NoChunk,
 }
 
 /// Simple result wrapper that uses SqpackError for errors
@@ -35,12 +57,40 @@ impl Debug for SqpackError {
         match self {
             Self::SqFileNotFound => write!(f, "SqPath not found in index!"),
             Self::IO(err) => write!(f, "Underlying IO Error ({:?})", err),
+            Self::NotSqPack => write!(f, "The underlying reader is not SqPack data"),
             Self::IndexReaderIsNotIndex => {
                 write!(f, "The underlying reader is not SqPack Index data")
             }
+            Self::DatReaderIsNotDat => write!(f, "The underlying reader is not SqPack .dat data"),
             Self::UnknownContentType(unk) => {
                 write!(f, "Unknown content type found while reading .dat: {}", unk)
             }
+            Self::UnknownPlatform(unk) => {
+                write!(f, "Unknown platform ID in SqPack header: {}", unk)
+            }
+            Self::InvalidSqPath(err) => write!(f, "Invalid SqPath: {}", err),
+            Self::MismatchedIndexFile(sqpath) => write!(
+                f,
+                "SqPath belongs to a different index file than the files already added: {}",
+                sqpath
+            ),
+        }
+    }
+}
+
+impl From<SqPathError> for SqpackError {
+    fn from(err: SqPathError) -> Self { SqpackError::InvalidSqPath(err) }
+}
+
+impl Error for SqPathError {}
+
+impl Display for SqPathError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::NoFolder => write!(f, "the path has no folder"),
+            Self::NoFileName => write!(f, "the path has no file name"),
+            Self::UnknownCategory(category) => write!(f, "unknown category {:?}", category),
+            Self::NoChunk => write!(f, "the path does not name its chunk"),
         }
     }
 }
@@ -1,9 +1,105 @@
+use crate::hash_consts::FFXIV_CRC_TABLES_8;
 pub use crate::hash_consts::{FFXIV_CRC_TABLE, FFXIV_SEED};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The ways of computing the FFXIV hash. They produce the same results, but differ in speed.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub enum HashImpl {
+    /// Processes one byte at a time, as [`compute_with_seed`](fn.compute_with_seed.html) does
+    Bytewise,
+    /// Processes eight bytes at a time with eight lookup tables, lowercasing all eight at once
+    #[default]
+    SliceBy8,
+}
+
+/// The implementation used by [`compute_str`](fn.compute_str.html) and
+/// [`compute_str_lower`](fn.compute_str_lower.html), stored as its index
+static DEFAULT_IMPL: AtomicU8 = AtomicU8::new(HashImpl::SliceBy8 as u8);
+
+impl HashImpl {
+    /// Returns the implementation used to hash paths, which is `SliceBy8` unless changed with
+    /// [`set_default`](#method.set_default).
+    pub fn get_default() -> HashImpl {
+        match DEFAULT_IMPL.load(Ordering::Relaxed) {
+            0 => HashImpl::Bytewise,
+            _ => HashImpl::SliceBy8,
+        }
+    }
+
+    /// Changes the implementation used to hash paths for the whole process.
+    pub fn set_default(hash_impl: HashImpl) {
+        DEFAULT_IMPL.store(hash_impl as u8, Ordering::Relaxed)
+    }
+
+    /// Computes the FFXIV hash of `buffer` from `seed` with this implementation. If `lower` is
+    /// true, the hash will be calculated as if all uppercase alphabet characters were lowercase.
+    ///
+    /// # Examples
+    /// ```
+    /// use sqpack::hash::{HashImpl, FFXIV_SEED};
+    /// let file_hash = HashImpl::SliceBy8.compute(FFXIV_SEED, b"BGM_System_Title.scd", true);
+    /// assert_eq!(file_hash, 0xE3B71579);
+    /// ```
+    pub fn compute(self, seed: u32, buffer: &[u8], lower: bool) -> u32 {
+        match self {
+            HashImpl::Bytewise => bytewise(seed, buffer, lower),
+            HashImpl::SliceBy8 => slice_by_8(seed, buffer, lower),
+        }
+    }
+}
+
+/// Hashes one byte at a time without the bounds checks of `compute_with_seed`.
+fn bytewise(seed: u32, buffer: &[u8], lower: bool) -> u32 {
+    buffer.iter().fold(seed, |crc, &b| {
+        let b = if lower { b.to_ascii_lowercase() } else { b };
+        (crc >> 8) ^ FFXIV_CRC_TABLE[(b ^ crc as u8) as usize]
+    })
+}
+
+/// Hashes eight bytes at a time, finishing the remainder one byte at a time.
+fn slice_by_8(seed: u32, buffer: &[u8], lower: bool) -> u32 {
+    let t = &FFXIV_CRC_TABLES_8;
+    let chunks = buffer.chunks_exact(8);
+    let remainder = chunks.remainder();
+    let mut crc = seed;
+    for chunk in chunks {
+        let mut word = u64::from_le_bytes(chunk.try_into().unwrap());
+        if lower {
+            word = lowercase_word(word);
+        }
+        let one = word as u32 ^ crc;
+        let two = (word >> 32) as u32;
+        crc = t[7][(one & 0xFF) as usize]
+            ^ t[6][((one >> 8) & 0xFF) as usize]
+            ^ t[5][((one >> 16) & 0xFF) as usize]
+            ^ t[4][(one >> 24) as usize]
+            ^ t[3][(two & 0xFF) as usize]
+            ^ t[2][((two >> 8) & 0xFF) as usize]
+            ^ t[1][((two >> 16) & 0xFF) as usize]
+            ^ t[0][(two >> 24) as usize];
+    }
+    bytewise(crc, remainder, lower)
+}
+
+/// Lowercases the ASCII uppercase letters among the eight bytes of `word`, leaving other bytes
+/// untouched.
+fn lowercase_word(word: u64) -> u64 {
+    const ONES: u64 = 0x0101_0101_0101_0101;
+    const HIGH: u64 = ONES * 0x80;
+    let low_bits = word & !HIGH;
+    // the high bit of each byte is set if the byte is at least 'A', or more than 'Z'
+    let at_least_a = low_bits + ONES * (0x80 - b'A' as u64);
+    let above_z = low_bits + ONES * (0x7F - b'Z' as u64);
+    let upper = (at_least_a ^ above_z) & !word & HIGH;
+    word ^ (upper >> 2)
+}
 
 /// Computes a hash from a seed, a CRC table, a buffer, the start index of the buffer,
 /// and the size of the slice to hash. Typically you would not need to call this, for most uses
 /// call [`SqPath::sq_index_hash`](../sqpath/struct.SqPath.html#method.sq_index_hash) instead. If
 /// `lower` is true, the hash will be calculated as if all uppercase alphabet characters were lowercase.
+/// This processes one byte at a time; [`HashImpl`](enum.HashImpl.html) computes the same hash
+/// faster with the FFXIV table.
 ///
 /// # Examples
 /// ```
@@ -22,19 +118,20 @@ pub fn compute_with_seed(
     let mut crc = seed;
     for i in start..size + start {
         let mut b = buffer[i as usize];
-        b ^= if lower && b >= 0x41 && b <= 0x5a {
+        b ^= if lower && (0x41..=0x5a).contains(&b) {
             0x20
         } else {
             0x00
         };
-        crc = (crc >> 8) ^ table[(b as u8 ^ crc as u8) as usize];
+        crc = (crc >> 8) ^ table[(b ^ crc as u8) as usize];
     }
     crc
 }
 
 /// Computes a string's hash with the default seed used for FFXIV. Unless you're re-implementing
 /// certain parts of this library, you shouldn't need to use this. Use [`SqPath::sq_index_hash`](../sqpath/struct.SqPath.html#method.sq_index_hash)
-/// instead. This function will simply convert the string to bytes and pass it to [`compute_with_seed`](fn.compute_with_seed.html).
+/// instead. This function will simply convert the string to bytes and hash them with the
+/// [default implementation](enum.HashImpl.html#method.get_default).
 ///
 /// # Examples
 /// ```
@@ -43,19 +140,13 @@ pub fn compute_with_seed(
 /// assert_eq!(file_hash, 0xE3B71579);
 /// ```
 pub fn compute_str<S: AsRef<str> + ?Sized>(val: &S) -> u32 {
-    compute_with_seed(
-        FFXIV_SEED,
-        &FFXIV_CRC_TABLE,
-        val.as_ref().as_bytes(),
-        0,
-        val.as_ref().as_bytes().len() as u32,
-        false,
-    )
+    HashImpl::get_default().compute(FFXIV_SEED, val.as_ref().as_bytes(), false)
 }
 
 /// Computes a string's hash with the default seed used for FFXIV. Unless you're re-implementing
 /// certain parts of this library, you shouldn't need to use this. Use [`SqPath::sq_index_hash`](../sqpath/struct.SqPath.html#method.sq_index_hash)
-/// instead. This function will simply convert the string to bytes and pass it to [`compute_with_seed`](fn.compute_with_seed.html).
+/// instead. This function will simply convert the string to bytes and hash them with the
+/// [default implementation](enum.HashImpl.html#method.get_default).
 ///
 /// # Examples
 /// ```
@@ -64,19 +155,91 @@ pub fn compute_str<S: AsRef<str> + ?Sized>(val: &S) -> u32 {
 /// assert_eq!(file_hash, 0xE3B71579);
 /// ```
 pub fn compute_str_lower<S: AsRef<str> + ?Sized>(val: &S) -> u32 {
-    compute_with_seed(
-        FFXIV_SEED,
-        &FFXIV_CRC_TABLE,
-        val.as_ref().as_bytes(),
-        0,
-        val.as_ref().as_bytes().len() as u32,
-        true,
-    )
+    HashImpl::get_default().compute(FFXIV_SEED, val.as_ref().as_bytes(), true)
+}
+
+/// Computes the FFXIV hash of `buffer` from `seed` at compile time. If `lower` is true, the hash
+/// will be calculated as if all uppercase alphabet characters were lowercase. This is slower than
+/// [`HashImpl`](enum.HashImpl.html) at runtime, so prefer it outside of constants.
+///
+/// # Examples
+/// ```
+/// use sqpack::hash::{compute_const, FFXIV_SEED};
+/// const FILE_HASH: u32 = compute_const(FFXIV_SEED, b"BGM_System_Title.scd", true);
+/// assert_eq!(FILE_HASH, 0xE3B71579);
+/// ```
+pub const fn compute_const(seed: u32, buffer: &[u8], lower: bool) -> u32 {
+    let mut crc = seed;
+    let mut i = 0;
+    while i < buffer.len() {
+        let b = if lower {
+            buffer[i].to_ascii_lowercase()
+        } else {
+            buffer[i]
+        };
+        crc = (crc >> 8) ^ FFXIV_CRC_TABLE[(b ^ crc as u8) as usize];
+        i += 1;
+    }
+    crc
+}
+
+/// Computes a string's hash with the default seed used for FFXIV at compile time. See
+/// [`compute_str`](fn.compute_str.html).
+///
+/// # Examples
+/// ```
+/// use sqpack::hash::compute_str_const;
+/// const FILE_HASH: u32 = compute_str_const("bgm_system_title.scd");
+/// assert_eq!(FILE_HASH, 0xE3B71579);
+/// ```
+pub const fn compute_str_const(val: &str) -> u32 {
+    compute_const(FFXIV_SEED, val.as_bytes(), false)
+}
+
+/// Computes a string's hash with the default seed used for FFXIV at compile time, as if it were
+/// lowercase. This is the hash `.index2` files use for full paths. See
+/// [`compute_str_lower`](fn.compute_str_lower.html).
+///
+/// # Examples
+/// ```
+/// use sqpack::hash::compute_str_lower_const;
+/// const PATH_HASH: u32 = compute_str_lower_const("music/ffxiv/BGM_System_Title.scd");
+/// assert_eq!(PATH_HASH, 0xE09DBB74);
+/// ```
+pub const fn compute_str_lower_const(val: &str) -> u32 {
+    compute_const(FFXIV_SEED, val.as_bytes(), true)
+}
+
+/// Computes the [`SqIndexHash`](../sqpath/struct.SqIndexHash.html) of a path at compile time,
+/// failing to compile if the path has no folder.
+///
+/// # Examples
+/// ```
+/// use sqpack::{sqhash, sqpath::SqIndexHash, SqPath};
+///
+/// const TITLE: SqIndexHash = sqhash!("music/ffxiv/BGM_System_Title.scd");
+/// assert_eq!(TITLE.folder_hash, 0x0AF269D6);
+/// assert_eq!(TITLE.file_hash, 0xE3B71579);
+/// assert_eq!(Some(TITLE), SqPath::new("music/ffxiv/bgm_system_title.scd").sq_index_hash());
+/// ```
+#[macro_export]
+macro_rules! sqhash {
+    ($path:expr) => {{
+        const HASH: $crate::sqpath::SqIndexHash = match $crate::sqpath::SqIndexHash::of($path) {
+            Some(hash) => hash,
+            None => panic!("sqhash! requires a path containing a folder"),
+        };
+        HASH
+    }};
 }
 
 #[cfg(test)]
 mod hash_tests {
-    use crate::hash;
+    use crate::{
+        hash::{self, HashImpl},
+        sqpath::SqIndexHash,
+        SqPath,
+    };
 
     #[test]
     fn case_eq() {
@@ -109,4 +272,84 @@ mod hash_tests {
         assert_eq!(hash::compute_str("music/ffxiv"), 0x0AF269D6);
         assert_eq!(hash::compute_str_lower("music/ffxiv"), 0x0AF269D6);
     }
+
+    #[test]
+    fn implementations_match_reference() {
+        // every byte value, including the letters and the bytes next to them, in every position
+        // of the eight byte words
+        let data = (0..=255u8).chain((0..=255u8).rev()).cycle().take(1200);
+        let data = data.collect::<Vec<_>>();
+        for start in 0..9 {
+            for len in (0..64).chain([511, 1000]) {
+                let buffer = &data[start..start + len];
+                for lower in [false, true] {
+                    let expected = hash::compute_with_seed(
+                        hash::FFXIV_SEED,
+                        &hash::FFXIV_CRC_TABLE,
+                        buffer,
+                        0,
+                        len as u32,
+                        lower,
+                    );
+                    for hash_impl in [HashImpl::Bytewise, HashImpl::SliceBy8] {
+                        assert_eq!(
+                            hash_impl.compute(hash::FFXIV_SEED, buffer, lower),
+                            expected,
+                            "{:?} of {} bytes at {} (lower: {})",
+                            hash_impl,
+                            len,
+                            start,
+                            lower
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn const_matches_runtime() {
+        const TITLE: SqIndexHash = sqhash!("music/ffxiv/BGM_System_Title.scd");
+        const FULL: u32 = hash::compute_str_lower_const("music/ffxiv/BGM_System_Title.scd");
+        assert_eq!(
+            Some(TITLE),
+            SqPath::new("music/ffxiv/BGM_System_Title.scd").sq_index_hash()
+        );
+        assert_eq!(
+            FULL,
+            SqPath::new("music/ffxiv/BGM_System_Title.scd").sq_index2_hash()
+        );
+
+        let paths = [
+            "",
+            "a",
+            "exd/root.exl",
+            "bg/ffxiv/sea_s1/twn/s1t1/level/bg.lgb",
+            "chara/equipment/e0001/model/c0201e0001_top.mdl",
+            "ui/icon/000000/000001_hr1.tex",
+            "nofolder",
+            "/leading",
+            "trailing/",
+            "Mixed/CASE/Path_With_Digits_0123456789.TeX",
+        ];
+        for path in paths {
+            assert_eq!(hash::compute_str_const(path), hash::compute_str(path));
+            assert_eq!(
+                hash::compute_str_lower_const(path),
+                hash::compute_str_lower(path)
+            );
+            assert_eq!(SqIndexHash::of(path), SqPath::new(path).sq_index_hash());
+        }
+    }
+
+    #[test]
+    fn lowercases_words() {
+        let word = u64::from_le_bytes(*b"@AZ[`az{");
+        assert_eq!(hash::lowercase_word(word).to_le_bytes(), *b"@az[`az{");
+        let word = u64::from_le_bytes([0xC1, 0xDA, 0x41, 0x5A, 0xFF, 0x80, 0x00, 0x7F]);
+        assert_eq!(
+            hash::lowercase_word(word).to_le_bytes(),
+            [0xC1, 0xDA, 0x61, 0x7A, 0xFF, 0x80, 0x00, 0x7F]
+        );
+    }
 }
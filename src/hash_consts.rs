@@ -36,3 +36,20 @@ pub const FFXIV_CRC_TABLE: [u32; 256] = [
     0xBDBDF21C, 0xCABAC28A, 0x53B39330, 0x24B4A3A6, 0xBAD03605, 0xCDD70693, 0x54DE5729, 0x23D967BF,
     0xB3667A2E, 0xC4614AB8, 0x5D681B02, 0x2A6F2B94, 0xB40BBE37, 0xC30C8EA1, 0x5A05DF1B, 0x2D02EF8D,
 ];
+
+/// Tables for processing eight bytes at a time. The first table is `FFXIV_CRC_TABLE`, and each
+/// following table advances the entries of the previous one by another byte of zeroes.
+pub(crate) const FFXIV_CRC_TABLES_8: [[u32; 256]; 8] = {
+    let mut tables = [FFXIV_CRC_TABLE; 8];
+    let mut k = 1;
+    while k < 8 {
+        let mut i = 0;
+        while i < 256 {
+            let prev = tables[k - 1][i];
+            tables[k][i] = (prev >> 8) ^ FFXIV_CRC_TABLE[(prev & 0xFF) as usize];
+            i += 1;
+        }
+        k += 1;
+    }
+    tables
+};
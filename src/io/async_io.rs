@@ -0,0 +1,339 @@
+use crate::{
+    error::SqResult,
+    io::{
+        dat::{sqfile::dat_path, ContentType, SharedDat, SharedDatReader, SqFile},
+        header::SqPackHeader,
+        index::{IndexCache, IndexFileEntry, IndexReader, SegmentInfo},
+        verify::VerifyReport,
+    },
+    sqpath::Platform,
+};
+use std::{
+    future::Future,
+    io::{Cursor, Error as IOError, ErrorKind, Read, Seek, SeekFrom},
+    path::Path,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncSeek, ReadBuf},
+    task::{spawn_blocking, JoinHandle},
+};
+
+/// The most data read ahead by a single read from the blocking pool
+const MAX_READ_AHEAD: usize = 0x20000;
+
+/// An asynchronous version of [`SqFile`](../dat/struct.SqFile.html), implementing tokio's
+/// [`AsyncRead`] and [`AsyncSeek`]. Reading from the .dat file and decompressing its blocks is
+/// done on tokio's blocking pool, so the executor is never blocked. Data is read ahead in chunks,
+/// so small reads do not each require a trip to the blocking pool.
+///
+/// # Examples
+/// ```no_run
+/// use sqpack::io::{async_io::{AsyncIndexLoader, AsyncSqFile}};
+/// use tokio::io::AsyncReadExt;
+///
+/// # async fn example() -> sqpack::error::SqResult<()> {
+/// let index_path = "/home/uwu/ffxiv/game/sqpack/ffxiv/0c0000.win32.index";
+/// let cache = AsyncIndexLoader::open(index_path).await?.cache()?;
+/// let entry = *cache.get("music/ffxiv/BGM_System_Title.scd").unwrap();
+/// let mut file = AsyncSqFile::open_entry(index_path, entry).await?;
+/// let mut data = Vec::with_capacity(file.total_size());
+/// file.read_to_end(&mut data).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncSqFile {
+    /// The synchronous reader, or `None` while it is being used by a blocking task
+    file: Option<SqFile<SharedDatReader>>,
+    /// The blocking task currently reading or seeking the file
+    task: Option<JoinHandle<(SqFile<SharedDatReader>, Operation)>>,
+    /// Data that has been read ahead but not yet returned
+    buffer: Cursor<Vec<u8>>,
+    /// The position within the decompressed file, as seen by the caller
+    position: u64,
+    /// The size of the decompressed file
+    total_size: usize,
+    /// The kind of content stored within the file
+    content_type: ContentType,
+}
+
+/// The result of a blocking operation on the synchronous reader
+enum Operation {
+    Read(Result<Vec<u8>, IOError>),
+    Seek(Result<u64, IOError>),
+}
+
+impl AsyncSqFile {
+    /// Opens a file within the SqPack given the path to the index file the entry was read from.
+    /// See [`SqFile::open_entry`](../dat/struct.SqFile.html#method.open_entry).
+    pub async fn open_entry<P: AsRef<Path>>(
+        index_path: P,
+        entry: IndexFileEntry,
+    ) -> SqResult<AsyncSqFile> {
+        let dat_path = dat_path(index_path.as_ref(), entry.dat_file)?;
+        let dat = unblock(move || SharedDat::open(dat_path)).await??;
+        Self::open_shared(&dat, entry).await
+    }
+
+    /// Opens a file within a .dat file that is shared between tasks. See
+    /// [`SqFile::open_shared`](../dat/struct.SqFile.html#method.open_shared).
+    pub async fn open_shared(dat: &SharedDat, entry: IndexFileEntry) -> SqResult<AsyncSqFile> {
+        let dat = dat.clone();
+        let file = unblock(move || SqFile::open_shared(&dat, entry)).await??;
+        Ok(AsyncSqFile {
+            total_size: file.total_size(),
+            content_type: file.content_type(),
+            file: Some(file),
+            task: None,
+            buffer: Cursor::new(Vec::new()),
+            position: 0,
+        })
+    }
+
+    /// Retrieves the kind of content stored within this .dat file
+    pub fn content_type(&self) -> ContentType { self.content_type }
+
+    /// Retrieves the resulting size of this file stored within the SqPack.
+    /// This can be used to prepare an in-memory buffer.
+    pub fn total_size(&self) -> usize { self.total_size }
+
+    /// Number of read ahead bytes that have not yet been returned
+    fn buffered(&self) -> usize { self.buffer.get_ref().len() - self.buffer.position() as usize }
+
+    /// Waits for the running blocking task, if any, and puts the reader back. Read ahead data is
+    /// kept and the result of a seek is returned.
+    fn poll_task(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<u64>, IOError>> {
+        let Some(task) = self.task.as_mut() else {
+            return Poll::Ready(Ok(None));
+        };
+        let result = ready!(Pin::new(task).poll(cx));
+        self.task = None;
+        let (file, operation) = result.map_err(join_error)?;
+        self.file = Some(file);
+        match operation {
+            Operation::Read(data) => {
+                self.buffer = Cursor::new(data?);
+                Poll::Ready(Ok(None))
+            }
+            Operation::Seek(position) => {
+                self.position = position?;
+                Poll::Ready(Ok(Some(self.position)))
+            }
+        }
+    }
+
+    /// Takes the synchronous reader to hand it to a blocking task.
+    fn take_file(&mut self) -> Result<SqFile<SharedDatReader>, IOError> {
+        self.file
+            .take()
+            .ok_or_else(|| IOError::other("the file was lost by a failed operation"))
+    }
+}
+
+impl AsyncRead for AsyncSqFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<(), IOError>> {
+        let this = self.get_mut();
+        loop {
+            let finished_read = this.task.is_some() && ready!(this.poll_task(cx))?.is_none();
+
+            if this.buffered() > 0 {
+                let start = this.buffer.position() as usize;
+                let len = this.buffered().min(buf.remaining());
+                buf.put_slice(&this.buffer.get_ref()[start..start + len]);
+                this.buffer.set_position((start + len) as u64);
+                this.position += len as u64;
+                return Poll::Ready(Ok(()));
+            }
+            // a read that returned no data has reached the end of the file
+            if finished_read || buf.remaining() == 0 || this.position >= this.total_size as u64 {
+                return Poll::Ready(Ok(()));
+            }
+
+            let mut file = this.take_file()?;
+            let len = buf.remaining().clamp(0x1000, MAX_READ_AHEAD);
+            this.task = Some(spawn_blocking(move || {
+                let mut data = vec![0; len];
+                let read = read_full(&mut file, &mut data).map(|n| {
+                    data.truncate(n);
+                    data
+                });
+                (file, Operation::Read(read))
+            }));
+        }
+    }
+}
+
+impl AsyncSeek for AsyncSqFile {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> Result<(), IOError> {
+        let this = self.get_mut();
+        if this.task.is_some() {
+            return Err(IOError::other(
+                "other file operation is pending, call poll_complete before start_seek",
+            ));
+        }
+        let target = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => (this.total_size as u64).checked_add_signed(offset),
+            SeekFrom::Current(offset) => this.position.checked_add_signed(offset),
+        }
+        .ok_or_else(|| {
+            IOError::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        this.buffer = Cursor::new(Vec::new());
+        let mut file = this.take_file()?;
+        this.task = Some(spawn_blocking(move || {
+            let result = file.seek(SeekFrom::Start(target));
+            (file, Operation::Seek(result))
+        }));
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<u64, IOError>> {
+        let this = self.get_mut();
+        ready!(this.poll_task(cx))?;
+        Poll::Ready(Ok(this.position))
+    }
+}
+
+/// A buffered loader for index files. The whole index file is read into memory asynchronously and
+/// then parsed synchronously with an [`IndexReader`](../index/struct.IndexReader.html) over that
+/// buffer, so none of its methods block, but memory use grows with the size of the index file.
+/// Index files are small next to .dat files, which [`AsyncSqFile`] streams instead.
+pub struct AsyncIndexLoader {
+    inner: IndexReader<Cursor<Vec<u8>>>,
+}
+
+impl AsyncIndexLoader {
+    /// Reads an index file asynchronously from `reader`, which should be positioned at the start
+    /// of the file.
+    ///
+    /// # Returns
+    /// `Err(SqpackError::IndexReaderIsNotIndex)` if `reader` did not contain a SqPack index file.
+    pub async fn new<R: AsyncRead + Unpin>(reader: R) -> SqResult<AsyncIndexLoader> {
+        let mut reader = reader;
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).await?;
+        Ok(AsyncIndexLoader {
+            inner: IndexReader::new(Cursor::new(data))?,
+        })
+    }
+
+    /// Reads the index file at `path` asynchronously.
+    pub async fn open<P: AsRef<Path>>(path: P) -> SqResult<AsyncIndexLoader> {
+        let data = tokio::fs::read(path).await?;
+        Ok(AsyncIndexLoader {
+            inner: IndexReader::new(Cursor::new(data))?,
+        })
+    }
+
+    /// Returns the SqPack header of the index file.
+    pub fn header(&self) -> &SqPackHeader { self.inner.header() }
+
+    /// Returns the platform the index file was built for.
+    pub fn platform(&self) -> Platform { self.inner.platform() }
+
+    /// Reads the descriptors of every segment of the index file.
+    pub fn segments(&mut self) -> SqResult<Vec<SegmentInfo>> { self.inner.segments() }
+
+    /// Reads the number of .dat files the index file refers to.
+    pub fn dat_count(&mut self) -> SqResult<u32> { self.inner.dat_count() }
+
+    /// Recomputes the SHA-1 digests of the index file. See
+    /// [`IndexReader::verify`](../index/struct.IndexReader.html#method.verify).
+    pub fn verify(&mut self) -> SqResult<VerifyReport> { self.inner.verify() }
+
+    /// Reads every file entry of the index file into a cache for lookups.
+    pub fn cache(&mut self) -> SqResult<IndexCache> { IndexCache::from_reader(&mut self.inner) }
+
+    /// Unwraps this loader, returning the `IndexReader` over the index file in memory.
+    pub fn into_inner(self) -> IndexReader<Cursor<Vec<u8>>> { self.inner }
+}
+
+/// Runs `f` on tokio's blocking pool, converting a panic or cancellation into an I/O error.
+async fn unblock<T, F>(f: F) -> Result<T, IOError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    spawn_blocking(f).await.map_err(join_error)
+}
+
+/// Converts the failure of a blocking task into an I/O error.
+fn join_error(err: tokio::task::JoinError) -> IOError { IOError::other(err) }
+
+/// Reads from `reader` until `buf` is full or the end of the reader is reached.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize, IOError> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod async_io_tests {
+    use crate::{
+        io::async_io::{AsyncIndexLoader, AsyncSqFile},
+        test_util::TempSqPack,
+    };
+    use std::io::SeekFrom;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    #[test]
+    fn reads_asynchronously() {
+        let large = (0..300_000u32).map(|i| (i % 11) as u8).collect::<Vec<_>>();
+        let temp = TempSqPack::new("async");
+        let index_path = temp.write(
+            "ffxiv/0c0000.win32.index",
+            &[("music/ffxiv/bgm_large.scd", &large)],
+        );
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let file = tokio::fs::File::open(&index_path).await.unwrap();
+            let mut index = AsyncIndexLoader::new(file).await.unwrap();
+            assert!(index.verify().unwrap().is_ok());
+            let cache = AsyncIndexLoader::open(&index_path)
+                .await
+                .unwrap()
+                .cache()
+                .unwrap();
+            assert_eq!(cache, index.cache().unwrap());
+
+            let entry = *cache.get("music/ffxiv/bgm_large.scd").unwrap();
+            let mut file = AsyncSqFile::open_entry(&index_path, entry).await.unwrap();
+            assert_eq!(file.total_size(), large.len());
+
+            let mut start = [0; 100];
+            file.read_exact(&mut start).await.unwrap();
+            assert_eq!(&start[..], &large[..100]);
+
+            assert_eq!(file.seek(SeekFrom::Current(-50)).await.unwrap(), 50);
+            let mut data = Vec::new();
+            file.read_to_end(&mut data).await.unwrap();
+            assert_eq!(&data[..], &large[50..]);
+
+            assert_eq!(file.seek(SeekFrom::End(-10)).await.unwrap(), 299_990);
+            data.clear();
+            file.read_to_end(&mut data).await.unwrap();
+            assert_eq!(&data[..], &large[299_990..]);
+            assert!(file.seek(SeekFrom::Current(-300_001)).await.is_err());
+        });
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    error::{SqResult, SqpackError},
+    io::{
+        dat::SqFile,
+        endian::Endian,
+        header::{SqPackFileType, SqPackHeader},
+        index::IndexFileEntry,
+        verify::{read_sha1, HashedRegion, VerifyReport},
+    },
+    sqpath::Platform,
+};
+use std::io::{Read, Seek, SeekFrom};
+
+/// The offset relative to the sqpack header end to find the size of the data section, in units
+/// of `DAT_DATA_UNIT` bytes
+pub(crate) const DAT_DATA_SIZE_OFFSET: u64 = 0x0C;
+
+/// The offset relative to the sqpack header end to find the SHA-1 hash of the data section
+pub(crate) const DAT_DATA_SHA1_OFFSET: u64 = 0x20;
+
+/// The unit the size of the data section is stored in
+pub(crate) const DAT_DATA_UNIT: u64 = 0x80;
+
+/// A reader over a whole .dat file, as opposed to [`SqFile`](struct.SqFile.html) which reads a
+/// single file stored within one.
+pub struct DatReader<R>
+where
+    R: Read + Seek,
+{
+    pub(self) inner: R,
+    header: SqPackHeader,
+    platform: Platform,
+}
+
+impl<R: Read + Seek> DatReader<R> {
+    /// Accepts a `Read + Seek` and wraps a `DatReader` around it.
+    ///
+    /// # Returns
+    /// `Ok(DatReader)` if `inner` was a `Read` over a SqPack .dat file
+    /// `Err(...)` if an I/O error occurred or if `inner` was not a `Read` over a SqPack .dat file.
+    pub fn new(inner: R) -> SqResult<Self> {
+        let mut inner = inner;
+        let header = match SqPackHeader::read(&mut inner) {
+            Ok(header) if header.file_type == SqPackFileType::Dat => header,
+            Ok(_) | Err(SqpackError::NotSqPack) => return Err(SqpackError::DatReaderIsNotDat),
+            Err(err) => return Err(err),
+        };
+        Ok(DatReader {
+            inner,
+            platform: header.platform()?,
+            header,
+        })
+    }
+
+    /// Returns the SqPack header of the .dat file.
+    pub fn header(&self) -> &SqPackHeader { &self.header }
+
+    /// Returns the platform the .dat file was built for. Values are read in its byte order.
+    pub fn platform(&self) -> Platform { self.platform }
+
+    /// Recomputes the SHA-1 digests of the headers and the data section of the .dat file,
+    /// reporting any that do not match the digests stored in the file. This reads the entire file.
+    pub fn verify(&mut self) -> SqResult<VerifyReport> {
+        let header_len = self.header.header_size as u64;
+        let endian = Endian::of(self.platform);
+        let mut report = VerifyReport::default();
+        report.check_header(&mut self.inner, HashedRegion::SqPackHeader, 0)?;
+        report.check_header(&mut self.inner, HashedRegion::DatHeader, header_len)?;
+
+        self.inner.seek(SeekFrom::Start(header_len))?;
+        let dat_header_len = endian.read_u32(&mut self.inner)? as u64;
+        self.inner
+            .seek(SeekFrom::Start(header_len + DAT_DATA_SIZE_OFFSET))?;
+        let data_size = endian.read_u32(&mut self.inner)? as u64 * DAT_DATA_UNIT;
+        let expected = read_sha1(&mut self.inner, header_len + DAT_DATA_SHA1_OFFSET)?;
+        report.check(
+            &mut self.inner,
+            HashedRegion::DatData,
+            header_len + dat_header_len,
+            data_size,
+            expected,
+        )?;
+        Ok(report)
+    }
+
+    /// Opens the file described by `index_entry` for reading, consuming this reader.
+    pub fn open_file(self, index_entry: IndexFileEntry) -> SqResult<SqFile<R>> {
+        SqFile::open_reader_for(self.inner, index_entry, self.platform)
+    }
+
+    /// Unwraps this `DatReader`, returning the underlying reader.
+    pub fn into_inner(self) -> R { self.inner }
+}
+
+#[cfg(test)]
+mod dat_reader_tests {
+    use crate::{
+        error::SqpackError,
+        io::{
+            dat::DatReader,
+            header::{SqPackFileType, SqPackHeader},
+        },
+    };
+    use std::io::Cursor;
+
+    #[test]
+    fn reads_header() {
+        let header = SqPackHeader::new(SqPackFileType::Dat);
+        let dat = Cursor::new(header.to_bytes().unwrap().into_vec());
+        assert_eq!(DatReader::new(dat).unwrap().header(), &header);
+
+        let index = SqPackHeader::new(SqPackFileType::Index);
+        let index = Cursor::new(index.to_bytes().unwrap().into_vec());
+        assert!(matches!(
+            DatReader::new(index),
+            Err(SqpackError::DatReaderIsNotDat)
+        ));
+    }
+}
@@ -1,19 +1,27 @@
 use std::convert::TryFrom;
 
-mod sqfile;
-pub use self::sqfile::SqFile;
+pub(crate) mod dat_reader;
+mod model;
+mod shared;
+pub(crate) mod sqfile;
+mod texture;
+pub use self::{
+    dat_reader::DatReader,
+    shared::{SharedDat, SharedDatReader},
+    sqfile::SqFile,
+};
 use crate::error::SqpackError;
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 /// The type of content stored within a file stored within a .dat file of a SqPack
 pub enum ContentType {
-    /// An empty placeholder file **(currently unsupported)**
+    /// An empty placeholder file, read as a file with no data
     Empty,
     /// Binary data file (typically anything not a Model or Texture)
     Binary,
-    /// Model data file **(currently unsupported)**
+    /// Model data file, read as a reconstructed .mdl file
     Model,
-    /// Texture data file **(currently unsupported)**
+    /// Texture data file, read as a reconstructed .tex file
     Texture,
 }
 
@@ -22,10 +30,10 @@ impl TryFrom<u32> for ContentType {
 
     fn try_from(value: u32) -> Result<Self, Self::Error> {
         match value {
-            // 1 => Ok(ContentType::Empty),
+            1 => Ok(ContentType::Empty),
             2 => Ok(ContentType::Binary),
-            // 3 => Ok(ContentType::Model),
-            // 4 => Ok(ContentType::Texture),
+            3 => Ok(ContentType::Model),
+            4 => Ok(ContentType::Texture),
             unk => Err(SqpackError::UnknownContentType(unk)),
         }
     }
@@ -0,0 +1,272 @@
+use crate::{
+    error::SqResult,
+    io::{
+        dat::sqfile::{add_block_size, BlockTableEntry, DatInfo, FileLayout},
+        endian::Endian,
+        index::IndexFileEntry,
+    },
+};
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+/// The number of levels of detail a model may have
+const MODEL_LODS: usize = 3;
+
+/// The number of chunks a model's data is split into: the stack, the runtime, and the vertex,
+/// edge geometry and index buffers of each level of detail.
+const MODEL_CHUNKS: usize = 2 + 3 * MODEL_LODS;
+
+/// The size of the model data header within the .dat file, not including the block size table
+const MODEL_INFO_SIZE: usize = 0xD0;
+
+/// The size of the header of a reconstructed .mdl file
+const MDL_HEADER_SIZE: u32 = 0x44;
+
+/// Indices of the chunks of a model, as they are ordered in the model data header
+const STACK: usize = 0;
+const RUNTIME: usize = 1;
+const VERTEX_BUFFER: usize = 2;
+const EDGE_GEOMETRY: usize = VERTEX_BUFFER + MODEL_LODS;
+const INDEX_BUFFER: usize = EDGE_GEOMETRY + MODEL_LODS;
+
+/// The model data header that follows the common data header of Model content
+struct ModelInfo {
+    version: u32,
+    sizes: [u32; MODEL_CHUNKS],
+    offsets: [u32; MODEL_CHUNKS],
+    block_indices: [u16; MODEL_CHUNKS],
+    block_counts: [u16; MODEL_CHUNKS],
+    vertex_declaration_count: u16,
+    material_count: u16,
+    lod_count: u8,
+    index_buffer_streaming: u8,
+    edge_geometry: u8,
+}
+
+impl ModelInfo {
+    /// Reads the model data header from a reader opened to a .dat file containing `index_entry`.
+    fn read<R>(reader: &mut R, index_entry: &IndexFileEntry, endian: Endian) -> SqResult<ModelInfo>
+    where
+        R: Read + Seek,
+    {
+        reader.seek(SeekFrom::Start(index_entry.data_offset))?;
+        let mut buffer = {
+            let mut buf = crate::buffer(MODEL_INFO_SIZE);
+            reader.read_exact(buf.as_mut())?;
+            Cursor::new(buf)
+        };
+
+        buffer.seek(SeekFrom::Start(0x14))?;
+        let version = endian.read_u32(&mut buffer)?;
+
+        let mut sizes = [0; MODEL_CHUNKS];
+        endian.read_u32_into(&mut buffer, &mut sizes)?;
+        // skip the compressed sizes, the blocks are located through the block size table instead
+        buffer.seek(SeekFrom::Current(4 * MODEL_CHUNKS as i64))?;
+        let mut offsets = [0; MODEL_CHUNKS];
+        endian.read_u32_into(&mut buffer, &mut offsets)?;
+        let mut block_indices = [0; MODEL_CHUNKS];
+        endian.read_u16_into(&mut buffer, &mut block_indices)?;
+        let mut block_counts = [0; MODEL_CHUNKS];
+        endian.read_u16_into(&mut buffer, &mut block_counts)?;
+
+        Ok(ModelInfo {
+            version,
+            sizes,
+            offsets,
+            block_indices,
+            block_counts,
+            vertex_declaration_count: endian.read_u16(&mut buffer)?,
+            material_count: endian.read_u16(&mut buffer)?,
+            lod_count: buffer.read_u8()?,
+            index_buffer_streaming: buffer.read_u8()?,
+            edge_geometry: buffer.read_u8()?,
+        })
+    }
+}
+
+/// Reads the layout of a Model file, computing the blocks of each chunk and rebuilding the .mdl
+/// header that precedes them.
+pub(super) fn read_model_layout<R>(
+    reader: &mut R,
+    index_entry: &IndexFileEntry,
+    dat_info: &DatInfo,
+) -> SqResult<FileLayout>
+where
+    R: Read + Seek,
+{
+    let endian = dat_info.endian;
+    let info = ModelInfo::read(reader, index_entry, endian)?;
+
+    // the block size table follows the model data header
+    let total_blocks: usize = info.block_counts.iter().map(|&c| c as usize).sum();
+    let mut block_sizes = vec![0u16; total_blocks];
+    endian.read_u16_into(reader, &mut block_sizes)?;
+
+    // Locates the blocks of a chunk, returning the decompressed size of the chunk given by the
+    // model data header. The sizes of the individual blocks are only read once they are needed.
+    let mut blocks = Vec::with_capacity(total_blocks);
+    let read_chunk = |chunk: usize, blocks: &mut Vec<BlockTableEntry>| -> SqResult<u32> {
+        let mut offset = info.offsets[chunk];
+        for i in 0..info.block_counts[chunk] as usize {
+            let block_size = *block_sizes
+                .get(info.block_indices[chunk] as usize + i)
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidData))?;
+            blocks.push(BlockTableEntry {
+                offset,
+                block_size,
+                decompressed_size: None,
+            });
+            offset = add_block_size(offset, block_size as u32)?;
+        }
+        Ok(info.sizes[chunk])
+    };
+
+    let stack_size = read_chunk(STACK, &mut blocks)?;
+    let runtime_size = read_chunk(RUNTIME, &mut blocks)?;
+
+    let mut position = add_block_size(add_block_size(MDL_HEADER_SIZE, stack_size)?, runtime_size)?;
+    let mut vertex_offsets = [0u32; MODEL_LODS];
+    let mut vertex_sizes = [0u32; MODEL_LODS];
+    let mut index_offsets = [0u32; MODEL_LODS];
+    let mut index_sizes = [0u32; MODEL_LODS];
+    for lod in 0..MODEL_LODS {
+        // a level of detail that shares its data with the previous one has an offset of 0
+        if info.block_counts[VERTEX_BUFFER + lod] != 0
+            && (lod == 0 || position != vertex_offsets[lod - 1])
+        {
+            vertex_offsets[lod] = position;
+        }
+        vertex_sizes[lod] = read_chunk(VERTEX_BUFFER + lod, &mut blocks)?;
+        position = add_block_size(position, vertex_sizes[lod])?;
+
+        position = add_block_size(position, read_chunk(EDGE_GEOMETRY + lod, &mut blocks)?)?;
+
+        if info.block_counts[INDEX_BUFFER + lod] != 0
+            && (lod == 0 || position != index_offsets[lod - 1])
+        {
+            index_offsets[lod] = position;
+        }
+        index_sizes[lod] = read_chunk(INDEX_BUFFER + lod, &mut blocks)?;
+        position = add_block_size(position, index_sizes[lod])?;
+    }
+
+    // rebuild the .mdl header
+    let mut header = Vec::with_capacity(MDL_HEADER_SIZE as usize);
+    endian.write_u32(&mut header, info.version)?;
+    endian.write_u32(&mut header, stack_size)?;
+    endian.write_u32(&mut header, runtime_size)?;
+    endian.write_u16(&mut header, info.vertex_declaration_count)?;
+    endian.write_u16(&mut header, info.material_count)?;
+    for values in [vertex_offsets, index_offsets, vertex_sizes, index_sizes] {
+        for value in values {
+            endian.write_u32(&mut header, value)?;
+        }
+    }
+    header.write_u8(info.lod_count)?;
+    header.write_u8(info.index_buffer_streaming)?;
+    header.write_u8(info.edge_geometry)?;
+    header.write_u8(0)?;
+
+    Ok(FileLayout {
+        header: header.into_boxed_slice(),
+        blocks,
+        total_size: position as usize,
+    })
+}
+
+#[cfg(test)]
+mod model_tests {
+    use crate::io::dat::{
+        sqfile::{test_entry, write_uncompressed_block},
+        ContentType, SqFile,
+    };
+    use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+    use std::io::{Cursor, Read, Write};
+
+    #[test]
+    fn reconstructs_mdl() {
+        // chunks: stack, runtime, then one vertex and one index buffer for LOD 0
+        let chunks: [&[u8]; 4] = [b"stack", b"runtime!", b"vertices", b"idx"];
+        let chunk_ids = [0, 1, 2, 8];
+
+        let header_len = 0x100u32;
+        let mut data = Vec::new();
+        let mut sizes = vec![];
+        let mut offsets = [0u32; 11];
+        let mut block_indices = [0u16; 11];
+        let mut block_counts = [0u16; 11];
+        for (i, chunk) in chunks.iter().enumerate() {
+            offsets[chunk_ids[i]] = data.len() as u32;
+            block_indices[chunk_ids[i]] = i as u16;
+            block_counts[chunk_ids[i]] = 1;
+            sizes.push(write_uncompressed_block(&mut data, chunk));
+        }
+
+        let mut dat = Vec::new();
+        dat.write_u32::<LE>(header_len).unwrap();
+        dat.write_u32::<LE>(3).unwrap();
+        dat.write_u32::<LE>(0).unwrap();
+        dat.write_u32::<LE>(4).unwrap();
+        dat.write_u32::<LE>(4).unwrap();
+        dat.write_u32::<LE>(0x1000005).unwrap();
+        let mut chunk_sizes = [0u32; 11];
+        for (i, chunk) in chunks.iter().enumerate() {
+            chunk_sizes[chunk_ids[i]] = chunk.len() as u32;
+        }
+        chunk_sizes
+            .iter()
+            .for_each(|&v| dat.write_u32::<LE>(v).unwrap());
+        dat.resize(0x70, 0);
+        offsets
+            .iter()
+            .for_each(|&v| dat.write_u32::<LE>(v).unwrap());
+        block_indices
+            .iter()
+            .for_each(|&v| dat.write_u16::<LE>(v).unwrap());
+        block_counts
+            .iter()
+            .for_each(|&v| dat.write_u16::<LE>(v).unwrap());
+        dat.write_u16::<LE>(2).unwrap();
+        dat.write_u16::<LE>(1).unwrap();
+        dat.write_all(&[1, 0, 0, 0]).unwrap();
+        sizes.iter().for_each(|&v| dat.write_u16::<LE>(v).unwrap());
+        dat.resize(header_len as usize, 0);
+        dat.extend_from_slice(&data);
+
+        let mut file = SqFile::open_reader(Cursor::new(dat), test_entry()).unwrap();
+        assert_eq!(file.content_type(), ContentType::Model);
+        let mut mdl = Vec::new();
+        file.read_to_end(&mut mdl).unwrap();
+        assert_eq!(mdl.len(), file.total_size());
+        assert_eq!(&mdl[0x44..], b"stackruntime!verticesidx");
+
+        let mut header = Cursor::new(&mdl);
+        assert_eq!(header.read_u32::<LE>().unwrap(), 0x1000005);
+        assert_eq!(header.read_u32::<LE>().unwrap(), 5);
+        assert_eq!(header.read_u32::<LE>().unwrap(), 8);
+        assert_eq!(header.read_u16::<LE>().unwrap(), 2);
+        assert_eq!(header.read_u16::<LE>().unwrap(), 1);
+        let mut values = [0u32; 12];
+        header.read_u32_into::<LE>(&mut values).unwrap();
+        let vertex_offset = 0x44 + 5 + 8;
+        assert_eq!(
+            values,
+            [
+                vertex_offset,
+                0,
+                0,
+                vertex_offset + 8,
+                0,
+                0,
+                8,
+                0,
+                0,
+                3,
+                0,
+                0
+            ]
+        );
+        assert_eq!(header.read_u8().unwrap(), 1);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::{error::SqResult, io::dat::DatReader, sqpath::Platform};
+#[cfg(not(any(unix, windows)))]
+use std::sync::Mutex;
+use std::{
+    fs::File,
+    io::{Error as IOError, ErrorKind, Read, Seek, SeekFrom},
+    path::Path,
+    sync::Arc,
+};
+
+/// A .dat file that can be read from many threads at once. Reads are positional, so no locking is
+/// needed and every reader created from it keeps its own position.
+/// Cloning the handle shares the underlying file.
+///
+/// # Platform support
+/// Positional reads are only available on unix and windows. Elsewhere the file sits behind a
+/// mutex and each read seeks and reads under the lock, so reads from different threads are
+/// serialized there. Readers still keep their own positions.
+///
+/// # Examples
+/// ```no_run
+/// use sqpack::io::{dat::{SharedDat, SqFile}, index::IndexCache, index::IndexReader};
+/// use std::{fs::File, io::Read, thread};
+///
+/// let index_path = "/home/uwu/ffxiv/game/sqpack/ffxiv/0c0000.win32.index";
+/// let mut index = IndexReader::new(File::open(index_path).unwrap()).unwrap();
+/// let cache = IndexCache::from_reader(&mut index).unwrap();
+/// let dat = SharedDat::open("/home/uwu/ffxiv/game/sqpack/ffxiv/0c0000.win32.dat0").unwrap();
+///
+/// thread::scope(|scope| {
+///     for path in ["music/ffxiv/BGM_System_Title.scd", "music/ffxiv/BGM_System_Chara.scd"] {
+///         let entry = *cache.get(path).unwrap();
+///         let dat = &dat;
+///         scope.spawn(move || {
+///             let mut file = SqFile::open_shared(dat, entry).unwrap();
+///             let mut data = Vec::with_capacity(file.total_size());
+///             file.read_to_end(&mut data).unwrap();
+///         });
+///     }
+/// });
+/// ```
+#[derive(Clone, Debug)]
+pub struct SharedDat {
+    file: Arc<DatHandle>,
+    len: u64,
+    platform: Platform,
+}
+
+/// The handle to the .dat file, which only needs a lock on targets without positional reads
+#[cfg(any(unix, windows))]
+type DatHandle = File;
+#[cfg(not(any(unix, windows)))]
+type DatHandle = Mutex<File>;
+
+impl SharedDat {
+    /// Opens the .dat file at `path`. Its SqPack header is read once here, so files opened from
+    /// it do not each read it again.
+    ///
+    /// # Returns
+    /// `Err(SqpackError::DatReaderIsNotDat)` if the file is not a SqPack .dat file.
+    pub fn open<P: AsRef<Path>>(path: P) -> SqResult<SharedDat> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let platform = DatReader::new(&file)?.platform();
+        Ok(SharedDat {
+            file: Arc::new(DatHandle::from(file)),
+            len,
+            platform,
+        })
+    }
+
+    /// Returns the size of the .dat file in bytes.
+    pub fn len(&self) -> u64 { self.len }
+
+    /// Returns the platform the .dat file was built for, as named in its header.
+    pub fn platform(&self) -> Platform { self.platform }
+
+    /// Returns true if the .dat file is empty.
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    /// Creates a reader over the .dat file starting at the beginning of the file.
+    pub fn reader(&self) -> SharedDatReader {
+        SharedDatReader {
+            dat: self.clone(),
+            position: 0,
+        }
+    }
+
+    /// Reads bytes at `offset` into `buf` without moving any cursor.
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize, IOError> {
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::FileExt::read_at(&*self.file, buf, offset)
+        }
+        #[cfg(windows)]
+        {
+            // seek_read moves the cursor of the handle, but every read passes its own offset so
+            // the cursor is never relied upon.
+            std::os::windows::fs::FileExt::seek_read(&*self.file, buf, offset)
+        }
+        #[cfg(not(any(unix, windows)))]
+        {
+            // No positional reads on this target, so this is the one place reads take a lock.
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(offset))?;
+            file.read(buf)
+        }
+    }
+}
+
+/// A reader over a [`SharedDat`](struct.SharedDat.html) with its own position. It is cheap to
+/// create, and readers over the same file do not affect each other.
+#[derive(Clone, Debug)]
+pub struct SharedDatReader {
+    dat: SharedDat,
+    position: u64,
+}
+
+impl Read for SharedDatReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IOError> {
+        if self.position >= self.dat.len {
+            return Ok(0);
+        }
+        let read = self.dat.read_at(buf, self.position)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for SharedDatReader {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, IOError> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.dat.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or_else(|| {
+            IOError::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod shared_tests {
+    use crate::{
+        io::{
+            dat::{SharedDat, SqFile},
+            index::{IndexCache, IndexReader},
+        },
+        test_util::TempSqPack,
+        SqPack,
+    };
+    use std::{
+        fs::File,
+        io::{Read, Seek, SeekFrom},
+        thread,
+    };
+
+    #[test]
+    fn reads_from_threads() {
+        let files = (0..8u8)
+            .map(|i| {
+                let data = (0..20_000u32)
+                    .map(|b| (b % 7) as u8 ^ i)
+                    .collect::<Vec<_>>();
+                (format!("music/ffxiv/bgm_{}.scd", i), data)
+            })
+            .collect::<Vec<_>>();
+        let temp = TempSqPack::new("shared");
+        let index_path = temp.write("ffxiv/0c0000.win32.index", &files);
+
+        let mut index = IndexReader::new(File::open(&index_path).unwrap()).unwrap();
+        let cache = IndexCache::from_reader(&mut index).unwrap();
+        let dat = SharedDat::open(index_path.with_extension("dat0")).unwrap();
+
+        thread::scope(|scope| {
+            for (path, expected) in &files {
+                let entry = *cache.get(path.as_str()).unwrap();
+                let dat = &dat;
+                scope.spawn(move || {
+                    let mut file = SqFile::open_shared(dat, entry).unwrap();
+                    let mut data = Vec::new();
+                    file.read_to_end(&mut data).unwrap();
+                    assert_eq!(&data, expected);
+
+                    file.seek(SeekFrom::Start(12_345)).unwrap();
+                    let mut byte = [0];
+                    file.read_exact(&mut byte).unwrap();
+                    assert_eq!(byte[0], expected[12_345]);
+                });
+            }
+        });
+
+        let sqpack = SqPack::new(temp.root());
+        thread::scope(|scope| {
+            for (path, expected) in &files {
+                let sqpack = &sqpack;
+                scope.spawn(move || {
+                    let mut data = Vec::new();
+                    let mut file = sqpack.open_shared(path.as_str()).unwrap();
+                    file.read_to_end(&mut data).unwrap();
+                    assert_eq!(&data, expected);
+                });
+            }
+        });
+
+        let mut reader = dat.reader();
+        assert_eq!(reader.seek(SeekFrom::End(0)).unwrap(), dat.len());
+        assert_eq!(reader.read(&mut [0; 4]).unwrap(), 0);
+        assert!(reader.seek(SeekFrom::Current(-1)).is_ok());
+        assert!(reader.seek(SeekFrom::Start(0)).is_ok());
+        assert!(reader.seek(SeekFrom::Current(-1)).is_err());
+    }
+}
@@ -1,93 +1,228 @@
+#[cfg(feature = "mmap")]
+use crate::io::mmap::{MappedFile, MappedSlice};
 use crate::{
     error::{SqResult, SqpackError},
     io::{
-        dat::ContentType,
-        index::{IndexFileEntry, IndexReader},
+        dat::{
+            model::read_model_layout, texture::read_texture_layout, ContentType, DatReader,
+            SharedDat, SharedDatReader,
+        },
+        endian::Endian,
+        index::{IndexCache, IndexFileEntry, IndexReader},
     },
+    sqpath::Platform,
     SqPath,
 };
-use byteorder::{ReadBytesExt, LE};
 use flate2::bufread::DeflateDecoder;
 use std::{
     convert::TryInto,
     fs::File,
     io::{Cursor, Error as IOError, ErrorKind, Read, Seek, SeekFrom},
-    path::Path,
-    vec::IntoIter as VecIntoIter,
+    path::{Path, PathBuf},
 };
 
 /// Allows reading files within a SqPack using Rust's [`Read`][read]
 /// trait. Within the SqPack, files are compressed, so there is necessarily
-/// an allocated buffer.
+/// an allocated buffer. Random access is supported through [`Seek`][seek],
+/// which only decompresses the block containing the new position.
 ///
 /// [read]: https://doc.rust-lang.org/std/io/trait.Read.html
+/// [seek]: https://doc.rust-lang.org/std/io/trait.Seek.html
 pub struct SqFile<R: Read + Seek> {
     /// Inner reader
     inner: R,
     /// The index entry of the file we're reading
     index_entry: IndexFileEntry,
-    /// The blocks as a Vec's IntoIter that were read during reading the table
-    blocks: VecIntoIter<BlockTableEntry>,
+    /// The reconstructed file header that is read before any of the blocks
+    header: Cursor<Box<[u8]>>,
+    /// The blocks that were read during reading the table
+    blocks: Vec<BlockTableEntry>,
+    /// The position within the file at which each block starts, for as many blocks as their
+    /// sizes have been learned so far
+    block_starts: Vec<u64>,
+    /// The index of the next block to read
+    next_block: usize,
+    /// The current position within the file
+    position: u64,
+    /// The size of the file once it has been completely read
+    total_size: usize,
     /// The current block this file is currently reading. This contains the buffer.
     current_block: Option<ReadingBlock>,
     /// The information about the data in the dat file.
     dat_info: DatInfo,
+    /// The mapping of the dat file, if it was opened from one. Blocks are then read straight from
+    /// the mapping instead of being copied.
+    #[cfg(feature = "mmap")]
+    mapping: Option<MappedFile>,
 }
 
 impl SqFile<File> {
     /// Opens a file within the SqPack from a [`SqPath`](../../../sqpath/struct.SqPath.html).
-    /// Also needs a path to the SqPack directory on the OS.
+    /// Also needs a path to the SqPack directory on the OS. Paths that do not name their chunk are
+    /// searched for in every chunk of their category and expansion. The SqPack is assumed to be
+    /// built for Windows, see [`open_sqpath_for`](#method.open_sqpath_for).
     pub fn open_sqpath<SQ, P>(sqpath: SQ, sqpack: P) -> SqResult<SqFile<File>>
+    where
+        SQ: AsRef<SqPath>,
+        P: AsRef<Path>,
+    {
+        Self::open_sqpath_for(sqpath, sqpack, Platform::Win32)
+    }
+
+    /// Opens a file within a SqPack built for `platform`. See
+    /// [`open_sqpath`](#method.open_sqpath).
+    pub fn open_sqpath_for<SQ, P>(
+        sqpath: SQ,
+        sqpack: P,
+        platform: Platform,
+    ) -> SqResult<SqFile<File>>
+    where
+        SQ: AsRef<SqPath>,
+        P: AsRef<Path>,
+    {
+        let (index_path, entry) = find_entry(sqpath.as_ref(), sqpack.as_ref(), platform)?;
+        Self::open_entry(index_path, entry)
+    }
+
+    /// Opens a file within the SqPack from a [`SqPath`](../../../sqpath/struct.SqPath.html),
+    /// looking it up in `cache` instead of scanning the index file. `cache` must have been read from
+    /// the index file that `sqpath` resolves to. If the path does not name its chunk, the index
+    /// files of its category and expansion are searched to find which one that is. The SqPack is
+    /// assumed to be built for Windows, see
+    /// [`open_sqpath_cached_for`](#method.open_sqpath_cached_for).
+    pub fn open_sqpath_cached<SQ, P>(
+        sqpath: SQ,
+        sqpack: P,
+        cache: &IndexCache,
+    ) -> SqResult<SqFile<File>>
+    where
+        SQ: AsRef<SqPath>,
+        P: AsRef<Path>,
+    {
+        Self::open_sqpath_cached_for(sqpath, sqpack, cache, Platform::Win32)
+    }
+
+    /// Opens a file within a SqPack built for `platform`, looking it up in `cache`. See
+    /// [`open_sqpath_cached`](#method.open_sqpath_cached).
+    pub fn open_sqpath_cached_for<SQ, P>(
+        sqpath: SQ,
+        sqpack: P,
+        cache: &IndexCache,
+        platform: Platform,
+    ) -> SqResult<SqFile<File>>
     where
         SQ: AsRef<SqPath>,
         P: AsRef<Path>,
     {
         let sqpath = sqpath.as_ref();
-        let sqpack = sqpack.as_ref();
+        let entry = *cache.get(sqpath).ok_or(SqpackError::SqFileNotFound)?;
+        let parsed = sqpath.parse()?;
+        let index_path = match parsed.chunk {
+            Some(_) => parsed.sqpack_index_path(sqpack, platform)?,
+            // the cache does not know which index file it was read from
+            None => find_entry(sqpath, sqpack.as_ref(), platform)?.0,
+        };
+        Self::open_entry(index_path, entry)
+    }
 
-        let index_hash = sqpath.sq_index_hash().ok_or(SqpackError::SqFileNotFound)?;
-        let mut index_path = sqpath
-            .sqpack_index_path(sqpack)
-            .ok_or(SqpackError::SqFileNotFound)?;
+    /// Opens a file within the SqPack given the path to the index file the entry was read from.
+    /// The .dat file containing the entry is located next to the index file, and is read in the
+    /// byte order of the platform named in its header.
+    pub fn open_entry<P: AsRef<Path>>(
+        index_path: P,
+        entry: IndexFileEntry,
+    ) -> SqResult<SqFile<File>> {
+        let dat_path = dat_path(index_path.as_ref(), entry.dat_file)?;
 
-        // Open a reader to find the right file
+        // Open the file and pass it to the reader function
+        let dat_file = File::open(dat_path)?;
+        DatReader::new(dat_file)?.open_file(entry)
+    }
+}
+
+impl SqFile<SharedDatReader> {
+    /// Opens a file within a .dat file that is shared between threads. Each file keeps its own
+    /// position, so any number of files can be read from the same .dat file at once.
+    pub fn open_shared(dat: &SharedDat, index_entry: IndexFileEntry) -> SqResult<Self> {
+        Self::open_reader_for(dat.reader(), index_entry, dat.platform())
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl SqFile<Cursor<MappedFile>> {
+    /// Opens a file within a .dat file that is mapped into memory. Blocks are decompressed
+    /// straight from the mapping rather than being copied into a buffer first.
+    pub fn open_mapped(dat: &MappedFile, index_entry: IndexFileEntry) -> SqResult<Self> {
+        let mut file = DatReader::new(Cursor::new(dat.clone()))?.open_file(index_entry)?;
+        file.mapping = Some(dat.clone());
+        Ok(file)
+    }
+}
+
+/// Finds the index entry of `sqpath` by scanning the index files it may be stored in, returning
+/// the path of the index file it was found in along with the entry.
+fn find_entry(
+    sqpath: &SqPath,
+    sqpack: &Path,
+    platform: Platform,
+) -> SqResult<(PathBuf, IndexFileEntry)> {
+    let parsed = sqpath.parse()?;
+    let index_hash = parsed.sq_index_hash();
+
+    // Search each index file the path may be in, which is only one unless it has no chunk
+    for index_path in parsed.index_paths(sqpack, platform)? {
         let mut index_reader = IndexReader::new(File::open(index_path.as_path())?)?;
-        let mut entry_opt = None;
         for file_res in index_reader.files()? {
             let file = file_res?;
             if file.path_hash == index_hash {
-                entry_opt = Some(file);
-                break;
+                return Ok((index_path, file));
             }
         }
-
-        // Get the entry, using it set the path's dat file number
-        let entry = entry_opt.ok_or(SqpackError::SqFileNotFound)?;
-        let mut ext = [0x64u8, 0x61, 0x74, 0x30];
-        ext[3] += entry.dat_file;
-        let ext = std::str::from_utf8(&ext).map_err(|_| IOError::from(ErrorKind::InvalidData))?;
-        index_path.set_extension(ext);
-
-        // Open the file and pass it to the reader function
-        let dat_file = File::open(index_path)?;
-        Self::open_reader(dat_file, entry)
     }
+    Err(SqpackError::SqFileNotFound)
+}
+
+/// Creates the path to the .dat file numbered `dat_file` which sits next to the index file at
+/// `index_path`.
+pub(crate) fn dat_path(index_path: &Path, dat_file: u8) -> SqResult<PathBuf> {
+    let mut ext = [0x64u8, 0x61, 0x74, 0x30];
+    ext[3] += dat_file;
+    let ext = std::str::from_utf8(&ext).map_err(|_| IOError::from(ErrorKind::InvalidData))?;
+    Ok(index_path.with_extension(ext))
 }
 
 impl<R: Read + Seek> SqFile<R> {
     /// Opens a file within the SqPack given a .dat reader. If the passed index
     /// entry is not found within this dat file, you will get corrupted data,
-    /// or more likely just get errors on reading.
+    /// or more likely just get errors on reading. The .dat file is assumed to be built for
+    /// Windows, see [`open_reader_for`](#method.open_reader_for).
     pub fn open_reader(reader: R, index_entry: IndexFileEntry) -> SqResult<SqFile<R>> {
+        Self::open_reader_for(reader, index_entry, Platform::Win32)
+    }
+
+    /// Opens a file within the SqPack given a reader over a .dat file built for `platform`. See
+    /// [`open_reader`](#method.open_reader).
+    pub fn open_reader_for(
+        reader: R,
+        index_entry: IndexFileEntry,
+        platform: Platform,
+    ) -> SqResult<SqFile<R>> {
         let mut reader = reader;
-        let dat_info = DatInfo::read_header(&mut reader, &index_entry)?;
-        let blocks = read_block_table_entries(&mut reader, &index_entry, &dat_info)?.into_iter();
+        let dat_info = DatInfo::read_header(&mut reader, &index_entry, Endian::of(platform))?;
+        let layout = FileLayout::read(&mut reader, &index_entry, &dat_info)?;
         Ok(SqFile {
             inner: reader,
             index_entry,
-            blocks,
+            block_starts: vec![layout.header.len() as u64],
+            header: Cursor::new(layout.header),
+            blocks: layout.blocks,
+            next_block: 0,
+            position: 0,
+            total_size: layout.total_size,
             current_block: None,
             dat_info,
+            #[cfg(feature = "mmap")]
+            mapping: None,
         })
     }
 
@@ -95,61 +230,29 @@ impl<R: Read + Seek> SqFile<R> {
     /// a new reader.
     pub fn reopen(self, index_entry: IndexFileEntry) -> SqResult<SqFile<R>> {
         let mut slf = self;
-        slf.dat_info = DatInfo::read_header(&mut slf.inner, &index_entry)?;
+        slf.dat_info = DatInfo::read_header(&mut slf.inner, &index_entry, slf.dat_info.endian)?;
         slf.current_block = None;
-        slf.blocks =
-            read_block_table_entries(&mut slf.inner, &index_entry, &slf.dat_info)?.into_iter();
+        let layout = FileLayout::read(&mut slf.inner, &index_entry, &slf.dat_info)?;
+        slf.block_starts = vec![layout.header.len() as u64];
+        slf.header = Cursor::new(layout.header);
+        slf.blocks = layout.blocks;
+        slf.next_block = 0;
+        slf.position = 0;
+        slf.total_size = layout.total_size;
         slf.index_entry = index_entry;
         Ok(slf)
     }
 
     /// Begins reading a block. Loads the block data into a buffer and
     /// determines if it needs decompression.
-    fn start_block(&mut self, entry: BlockTableEntry) -> Result<ReadingBlock, IOError> {
-        let block_offset = self.index_entry.data_offset + self.dat_info.header_len + entry.offset;
-        self.inner.seek(SeekFrom::Start(block_offset as u64))?;
-
-        // Read the header into a buffer
-        let mut header = [0u8; 0x10];
-        self.inner.read_exact(&mut header)?;
-        let mut cursor = Cursor::new(header);
-
-        // Read the header data
-        let block_header_len = cursor.read_u32::<LE>()?;
-        cursor.seek(SeekFrom::Current(4))?;
-        let compressed_len = cursor.read_u32::<LE>()?;
-        let decompressed_len = cursor.read_u32::<LE>()?;
-
-        // According to datamining research, if the compressed_len is < 32000,
-        // it is compressed. Otherwise it should be exactly 32000
-        let is_compressed = compressed_len < 32000;
-        if !is_compressed && compressed_len != 32000 {
-            return Err(IOError::from(ErrorKind::InvalidData));
-        }
+    fn start_block(&mut self, block: usize) -> Result<ReadingBlock, IOError> {
+        let header = self.read_block_header(block)?;
 
         // Determine the length of the data to read from the file
-        let final_length = if is_compressed {
-            if (entry.block_size as u32 + block_header_len) % BLOCK_PADDING != 0 {
-                compressed_len + BLOCK_PADDING
-                    - ((entry.block_size as u32 - block_header_len) % BLOCK_PADDING)
-            } else {
-                compressed_len
-            }
-        } else {
-            decompressed_len
-        };
+        let is_compressed = header.is_compressed();
+        let final_length = header.data_len(self.blocks[block].block_size);
 
-        // Create a buffer by creating a Vec and converting it into a boxed slice
-        // This unsafe call uses the exact same strategy as std's BufReader
-        let mut data = Vec::with_capacity(final_length as usize);
-        let mut data = unsafe {
-            data.set_len(final_length as usize);
-            data.into_boxed_slice()
-        };
-
-        // Read all of the data into the buffer
-        self.inner.read_exact(&mut data)?;
-        let data = Cursor::new(data);
+        let data = Cursor::new(self.read_block_data(final_length as usize)?);
         Ok(if is_compressed {
             ReadingBlock::Compressed(CompressedReadingBlock {
                 decoder: DeflateDecoder::new(data),
@@ -159,19 +262,57 @@ impl<R: Read + Seek> SqFile<R> {
         })
     }
 
-    /// Retrieves the kind of content stored within this .dat file
-    pub fn content_type(&self) -> ContentType { self.dat_info.content_type }
+    /// Reads the header of `block`, leaving the reader at the start of its data. The decompressed
+    /// size of the block is remembered, as it may not be known from the block table.
+    fn read_block_header(&mut self, block: usize) -> Result<BlockHeader, IOError> {
+        let entry = &mut self.blocks[block];
+        let block_offset = block_position(&self.index_entry, &self.dat_info, entry.offset)?;
+        self.inner.seek(SeekFrom::Start(block_offset))?;
+        let header = BlockHeader::read(&mut self.inner, self.dat_info.endian)?;
+        entry.decompressed_size = Some(header.decompressed_len);
+        Ok(header)
+    }
 
-    /// Retrieves the resulting size of this file stored within the SqPack.
-    /// This can be used to prepare an in-memory buffer.
-    pub fn total_size(&self) -> usize { self.dat_info.uncompressed_size as usize }
-}
+    /// Finds the position within the file at which `block` starts. The sizes of the blocks before
+    /// it that are not known yet are read from their block headers.
+    fn block_start(&mut self, block: usize) -> Result<u64, IOError> {
+        while self.block_starts.len() <= block {
+            let previous = self.block_starts.len() - 1;
+            let size = match self.blocks[previous].decompressed_size {
+                Some(size) => size,
+                None => self.read_block_header(previous)?.decompressed_len,
+            };
+            self.block_starts
+                .push(self.block_starts[previous] + size as u64);
+        }
+        Ok(self.block_starts[block])
+    }
 
-/// The padding between the block header and the block data
-const BLOCK_PADDING: u32 = 0x80;
+    /// Reads `len` bytes of block data from the current position of the reader. If the .dat file is
+    /// mapped, the data is borrowed from the mapping instead.
+    fn read_block_data(&mut self, len: usize) -> Result<BlockData, IOError> {
+        #[cfg(feature = "mmap")]
+        if let Some(mapping) = &self.mapping {
+            let start = self.inner.stream_position()?;
+            return mapping.slice(start, len).map(BlockData::Mapped);
+        }
 
-impl<R: Read + Seek> Read for SqFile<R> {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IOError> {
+        let mut data = crate::buffer(len);
+        self.inner.read_exact(&mut data)?;
+        Ok(BlockData::Owned(data))
+    }
+
+    /// Takes the next block to read from the block table, if any remain.
+    fn take_next_block(&mut self) -> Option<usize> {
+        let next = (self.next_block < self.blocks.len()).then_some(self.next_block);
+        if next.is_some() {
+            self.next_block += 1;
+        }
+        next
+    }
+
+    /// Reads from the blocks, moving on to the next block whenever the current one is exhausted.
+    fn read_blocks(&mut self, buf: &mut [u8]) -> Result<usize, IOError> {
         // check if we're in the middle of a block
         if let Some(current_block) = self.current_block.as_mut() {
             // read from the block
@@ -181,10 +322,10 @@ impl<R: Read + Seek> Read for SqFile<R> {
                     // if there was nothing left in the current block
                     // start reading the next block
                     // and recurse to read that block
-                    if let Some(next) = self.blocks.next() {
+                    if let Some(next) = self.take_next_block() {
                         let reading_block = self.start_block(next)?;
                         self.current_block.replace(reading_block);
-                        let r = self.read(buf)?;
+                        let r = self.read_blocks(buf)?;
                         Ok(r)
                     } else {
                         // if there was no other block, we're done
@@ -199,17 +340,94 @@ impl<R: Read + Seek> Read for SqFile<R> {
             }
         } else {
             // Not currently reading a block
-            if let Some(next) = self.blocks.next() {
+            if let Some(next) = self.take_next_block() {
                 // if there is a block to read, do that and recurse
                 let reading_block = self.start_block(next)?;
                 self.current_block.replace(reading_block);
-                self.read(buf)
+                self.read_blocks(buf)
             } else {
                 // otherwise done
                 Ok(0)
             }
         }
     }
+
+    /// Retrieves the kind of content stored within this .dat file
+    pub fn content_type(&self) -> ContentType { self.dat_info.content_type }
+
+    /// Retrieves the resulting size of this file stored within the SqPack.
+    /// This can be used to prepare an in-memory buffer.
+    pub fn total_size(&self) -> usize { self.total_size }
+}
+
+impl<R: Read + Seek> Read for SqFile<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IOError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // the reconstructed file header always comes before the blocks
+        let mut n = self.header.read(buf)?;
+        if n == 0 {
+            n = self.read_blocks(buf)?;
+        }
+        if n == 0 && self.dat_info.content_type == ContentType::Empty {
+            let remaining = (self.total_size as u64).saturating_sub(self.position);
+            n = buf.len().min(remaining.try_into().unwrap_or(usize::MAX));
+            buf[..n].fill(0);
+        }
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for SqFile<R> {
+    /// Seeks to a position within the decompressed file. Only the block containing the new
+    /// position is decompressed, using the block table to skip over the blocks before it.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, IOError> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => (self.total_size as u64).checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        }
+        .ok_or_else(|| {
+            IOError::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        let header_len = self.header.get_ref().len() as u64;
+        self.current_block = None;
+        if target < header_len {
+            self.header.set_position(target);
+            self.next_block = 0;
+        } else {
+            self.header.set_position(header_len);
+
+            if target < self.total_size as u64 && !self.blocks.is_empty() {
+                // find the block containing the target, the last block starting at or before it,
+                // only learning the sizes of blocks past the known ones if the target is beyond
+                let known = self.block_starts.partition_point(|&start| start <= target);
+                let mut block = known.saturating_sub(1);
+                if known == self.block_starts.len() {
+                    while block + 1 < self.blocks.len() && self.block_start(block + 1)? <= target {
+                        block += 1;
+                    }
+                }
+
+                self.next_block = block + 1;
+                let mut reading_block = self.start_block(block)?;
+                let skip = target - self.block_starts[block];
+                std::io::copy(&mut (&mut reading_block).take(skip), &mut std::io::sink())?;
+                self.current_block = Some(reading_block);
+            } else {
+                self.next_block = self.blocks.len();
+            }
+        }
+        self.position = target;
+        Ok(target)
+    }
 }
 
 /// Used to determine which type of block is being read
@@ -220,14 +438,31 @@ enum ReadingBlock {
     Uncompressed(UncompressedReadingBlock),
 }
 
+/// The data of a block, either read into a buffer or borrowed from a mapped .dat file
+enum BlockData {
+    Owned(Box<[u8]>),
+    #[cfg(feature = "mmap")]
+    Mapped(MappedSlice),
+}
+
+impl AsRef<[u8]> for BlockData {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            BlockData::Owned(data) => data,
+            #[cfg(feature = "mmap")]
+            BlockData::Mapped(data) => data.as_ref(),
+        }
+    }
+}
+
 /// Wraps a deflate decoder over a buffer of the block
 struct CompressedReadingBlock {
-    pub decoder: DeflateDecoder<Cursor<Box<[u8]>>>,
+    pub decoder: DeflateDecoder<Cursor<BlockData>>,
 }
 
 /// Wraps a cursor over a buffer of the block
 struct UncompressedReadingBlock {
-    pub buffer: Cursor<Box<[u8]>>,
+    pub buffer: Cursor<BlockData>,
 }
 
 // Dispatches read calls
@@ -249,51 +484,173 @@ impl Read for UncompressedReadingBlock {
 }
 
 /// Information about the file (the data header)
-struct DatInfo {
+pub(super) struct DatInfo {
     pub header_len: u32,
     pub content_type: ContentType,
     pub uncompressed_size: u32,
     pub blocks_len: u32,
+    /// The byte order of the .dat file
+    pub endian: Endian,
 }
 
 impl DatInfo {
     /// Reads the header from a reader given that its opened to a .dat file with the
-    /// provided index entry contained.
-    pub(crate) fn read_header<R>(reader: &mut R, index_entry: &IndexFileEntry) -> SqResult<DatInfo>
+    /// provided index entry contained, and byte order `endian`.
+    pub(crate) fn read_header<R>(
+        reader: &mut R,
+        index_entry: &IndexFileEntry,
+        endian: Endian,
+    ) -> SqResult<DatInfo>
     where
         R: Read + Seek,
     {
-        reader.seek(SeekFrom::Start(index_entry.data_offset as u64))?;
+        reader.seek(SeekFrom::Start(index_entry.data_offset))?;
         let mut buffer = {
             let mut buf = crate::buffer(24);
             reader.read_exact(buf.as_mut())?;
             Cursor::new(buf)
         };
-        let header_len = buffer.read_u32::<LE>()?;
-        let content_type: ContentType = buffer.read_u32::<LE>()?.try_into()?;
-        let uncompressed_size = buffer.read_u32::<LE>()?;
+        let header_len = endian.read_u32(&mut buffer)?;
+        let content_type: ContentType = endian.read_u32(&mut buffer)?.try_into()?;
+        let uncompressed_size = endian.read_u32(&mut buffer)?;
         buffer.seek(SeekFrom::Current(8))?;
-        let blocks_len = buffer.read_u32::<LE>()?;
+        let blocks_len = endian.read_u32(&mut buffer)?;
 
         Ok(DatInfo {
             header_len,
             content_type,
             uncompressed_size,
             blocks_len,
+            endian,
         })
     }
 }
 
 /// Data about a block of a SqFile
-struct BlockTableEntry {
+#[derive(Copy, Clone)]
+pub(super) struct BlockTableEntry {
     /// The offset relative to the end of the header of the SqFile
+    pub offset: u32,
+    /// The size of the block within the .dat file, including its header and padding
+    pub block_size: u16,
+    /// The size of the block's data once decompressed, or `None` if it is only found in the block
+    /// header
+    pub decompressed_size: Option<u32>,
+}
+
+/// The header found at the start of every block
+pub(super) struct BlockHeader {
+    /// The length of the block header
+    pub header_len: u32,
+    /// The length of the compressed data, or 32000 if the block is not compressed
+    pub compressed_len: u32,
+    /// The length of the data once decompressed
+    pub decompressed_len: u32,
+}
+
+impl BlockHeader {
+    /// Reads a block header in byte order `endian` from a reader positioned at the start of a
+    /// block.
+    pub fn read<R: Read>(reader: &mut R, endian: Endian) -> Result<BlockHeader, IOError> {
+        let mut header = [0u8; 0x10];
+        reader.read_exact(&mut header)?;
+        let mut cursor = Cursor::new(header);
+        let header_len = endian.read_u32(&mut cursor)?;
+        cursor.seek(SeekFrom::Current(4))?;
+        let compressed_len = endian.read_u32(&mut cursor)?;
+        let decompressed_len = endian.read_u32(&mut cursor)?;
+
+        // According to datamining research, if the compressed_len is < 32000,
+        // it is compressed. Otherwise it should be exactly 32000
+        if compressed_len > 32000 {
+            return Err(IOError::from(ErrorKind::InvalidData));
+        }
+        Ok(BlockHeader {
+            header_len,
+            compressed_len,
+            decompressed_len,
+        })
+    }
+
+    /// Whether the block data is DEFLATE compressed
+    pub fn is_compressed(&self) -> bool { self.compressed_len < 32000 }
+
+    /// The length of the data following the header of a block that is `block_size` bytes long
+    /// within the .dat file. Compressed data is read along with the padding after it, up to the
+    /// end of the block.
+    pub fn data_len(&self, block_size: u16) -> u32 {
+        if !self.is_compressed() {
+            return self.decompressed_len;
+        }
+        let padded_len = (block_size as u32).saturating_sub(self.header_len);
+        self.compressed_len.max(padded_len)
+    }
+}
+
+/// Describes how a file is laid out within a .dat file, and how to reconstruct it
+pub(super) struct FileLayout {
+    /// Data emitted before the blocks, such as a rebuilt file header
+    pub header: Box<[u8]>,
+    /// The blocks making up the file, in the order they are read
+    pub blocks: Vec<BlockTableEntry>,
+    /// The size of the reconstructed file
+    pub total_size: usize,
+}
+
+impl FileLayout {
+    /// Take a reader and an index entry and the SqFile's header info and read the layout
+    /// of the file according to its content type.
+    fn read<R>(reader: &mut R, index_entry: &IndexFileEntry, dat_info: &DatInfo) -> SqResult<Self>
+    where
+        R: Read + Seek,
+    {
+        match dat_info.content_type {
+            ContentType::Binary => Ok(FileLayout {
+                header: Box::new([]),
+                blocks: read_block_table_entries(reader, index_entry, dat_info)?,
+                total_size: dat_info.uncompressed_size as usize,
+            }),
+            ContentType::Model => read_model_layout(reader, index_entry, dat_info),
+            // placeholders have no data of their own, and read as zeros up to their declared size
+            ContentType::Empty => Ok(FileLayout {
+                header: Box::new([]),
+                blocks: Vec::new(),
+                total_size: dat_info.uncompressed_size as usize,
+            }),
+            ContentType::Texture => read_texture_layout(reader, index_entry, dat_info),
+        }
+    }
+}
+
+/// Computes the position within the .dat file that is `offset` bytes after the start of the
+/// entry's data header.
+pub(super) fn data_position(index_entry: &IndexFileEntry, offset: u64) -> Result<u64, IOError> {
+    index_entry
+        .data_offset
+        .checked_add(offset)
+        .ok_or_else(|| IOError::new(ErrorKind::InvalidData, "data offset overflows"))
+}
+
+/// Adds a block size read from the .dat file to an offset or size, failing on overflow instead of
+/// trusting the file.
+pub(super) fn add_block_size(value: u32, size: u32) -> Result<u32, IOError> {
+    value
+        .checked_add(size)
+        .ok_or_else(|| IOError::new(ErrorKind::InvalidData, "block offset or size overflows"))
+}
+
+/// Computes the position within the .dat file of the block at `offset` relative to the end of the
+/// header of the SqFile.
+fn block_position(
+    index_entry: &IndexFileEntry,
+    dat_info: &DatInfo,
     offset: u32,
-    /// The compressed size of the block
-    block_size: u16,
+) -> Result<u64, IOError> {
+    data_position(index_entry, dat_info.header_len as u64 + offset as u64)
 }
 
 /// Take a reader and an index entry and the SqFile's header info and read the block
-/// table to produce a vector over the block information.
+/// table of a Binary file to produce a vector over the block information.
 fn read_block_table_entries<R>(
     reader: &mut R,
     index_entry: &IndexFileEntry,
@@ -302,17 +659,10 @@ fn read_block_table_entries<R>(
 where
     R: Read + Seek,
 {
-    reader.seek(SeekFrom::Start(index_entry.data_offset as u64 + 24))?;
+    reader.seek(SeekFrom::Start(data_position(index_entry, 24)?))?;
 
     let mut blocks = Vec::with_capacity(dat_info.blocks_len as usize);
 
-    assert_eq!(
-        dat_info.content_type,
-        ContentType::Binary,
-        "The content type is unsupported."
-    );
-    // TODO implement support for other content types
-
     let mut buffer = {
         let buf_size = 8 * dat_info.blocks_len as usize;
         // create a buffer for all the blocks and read in all at once
@@ -324,11 +674,232 @@ where
     // read the blocks
     for _ in 0..dat_info.blocks_len {
         blocks.push(BlockTableEntry {
-            offset: buffer.read_u32::<LE>()?,
-            block_size: buffer.read_u16::<LE>()?,
+            offset: dat_info.endian.read_u32(&mut buffer)?,
+            block_size: dat_info.endian.read_u16(&mut buffer)?,
+            decompressed_size: Some(dat_info.endian.read_u16(&mut buffer)? as u32),
         });
-        buffer.seek(SeekFrom::Current(2))?;
     }
 
     Ok(blocks)
 }
+
+/// Writes an uncompressed block containing `data` to `dat`, padded to 0x80 bytes. Returns the
+/// size of the block.
+#[cfg(test)]
+pub(super) fn write_uncompressed_block(dat: &mut Vec<u8>, data: &[u8]) -> u16 {
+    use byteorder::{WriteBytesExt, LE};
+
+    let start = dat.len();
+    dat.write_u32::<LE>(0x10).unwrap();
+    dat.write_u32::<LE>(0).unwrap();
+    dat.write_u32::<LE>(32000).unwrap();
+    dat.write_u32::<LE>(data.len() as u32).unwrap();
+    dat.extend_from_slice(data);
+    dat.resize((dat.len() + 0x7f) & !0x7f, 0);
+    (dat.len() - start) as u16
+}
+
+/// An index entry for a file at the start of a .dat file
+#[cfg(test)]
+pub(super) fn test_entry() -> IndexFileEntry {
+    IndexFileEntry {
+        path_hash: crate::sqpath::SqIndexHash {
+            folder_hash: 0,
+            file_hash: 0,
+        },
+        data_offset: 0,
+        dat_file: 0,
+    }
+}
+
+#[cfg(test)]
+mod sqfile_tests {
+    use crate::{
+        io::dat::{
+            sqfile::{test_entry, write_uncompressed_block},
+            ContentType, SqFile,
+        },
+        sqpath::Platform,
+    };
+    use byteorder::{WriteBytesExt, BE, LE};
+    use flate2::{write::DeflateEncoder, Compression};
+    use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+    /// Builds a Binary file made of uncompressed blocks containing `blocks`
+    fn binary_dat(blocks: &[&[u8]]) -> Vec<u8> {
+        let header_len = 0x80;
+        let mut data = Vec::new();
+        let mut table = Vec::new();
+        for block in blocks {
+            let offset = data.len() as u32;
+            let size = write_uncompressed_block(&mut data, block);
+            table.push((offset, size, block.len() as u16));
+        }
+
+        let mut dat = Vec::new();
+        dat.write_u32::<LE>(header_len).unwrap();
+        dat.write_u32::<LE>(2).unwrap();
+        dat.write_u32::<LE>(blocks.iter().map(|b| b.len() as u32).sum())
+            .unwrap();
+        dat.write_u32::<LE>(0).unwrap();
+        dat.write_u32::<LE>(0).unwrap();
+        dat.write_u32::<LE>(blocks.len() as u32).unwrap();
+        for (offset, size, decompressed_size) in table {
+            dat.write_u32::<LE>(offset).unwrap();
+            dat.write_u16::<LE>(size).unwrap();
+            dat.write_u16::<LE>(decompressed_size).unwrap();
+        }
+        dat.resize(header_len as usize, 0);
+        dat.extend_from_slice(&data);
+        dat
+    }
+
+    /// Reads `len` bytes from the current position of `file`
+    fn read_n<R: Read>(file: &mut R, len: usize) -> Vec<u8> {
+        let mut buf = vec![0; len];
+        file.read_exact(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn seek_within_blocks() {
+        let dat = binary_dat(&[b"0123456789", b"abcdefghij", b"ABCDE"]);
+        let mut file = SqFile::open_reader(Cursor::new(dat), test_entry()).unwrap();
+        assert_eq!(file.total_size(), 25);
+
+        assert_eq!(file.seek(SeekFrom::Start(12)).unwrap(), 12);
+        assert_eq!(read_n(&mut file, 4), b"cdef");
+        assert_eq!(file.stream_position().unwrap(), 16);
+
+        assert_eq!(file.seek(SeekFrom::Current(-10)).unwrap(), 6);
+        assert_eq!(read_n(&mut file, 6), b"6789ab");
+
+        assert_eq!(file.seek(SeekFrom::End(-3)).unwrap(), 22);
+        let mut rest = Vec::new();
+        file.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"CDE");
+
+        assert_eq!(file.seek(SeekFrom::Start(10)).unwrap(), 10);
+        assert_eq!(read_n(&mut file, 1), b"a");
+
+        assert_eq!(file.seek(SeekFrom::Start(0)).unwrap(), 0);
+        let mut all = Vec::new();
+        file.read_to_end(&mut all).unwrap();
+        assert_eq!(all, b"0123456789abcdefghijABCDE");
+    }
+
+    #[test]
+    fn seek_out_of_range() {
+        let dat = binary_dat(&[b"0123456789"]);
+        let mut file = SqFile::open_reader(Cursor::new(dat), test_entry()).unwrap();
+
+        assert!(file.seek(SeekFrom::End(-11)).is_err());
+        assert_eq!(file.seek(SeekFrom::End(5)).unwrap(), 15);
+        let mut rest = Vec::new();
+        assert_eq!(file.read_to_end(&mut rest).unwrap(), 0);
+    }
+
+    #[test]
+    fn empty_entry() {
+        let mut dat = Vec::new();
+        dat.write_u32::<LE>(0x80).unwrap();
+        dat.write_u32::<LE>(1).unwrap();
+        dat.write_u32::<LE>(0x200).unwrap();
+        dat.resize(0x80, 0);
+
+        let mut file = SqFile::open_reader(Cursor::new(dat.clone()), test_entry()).unwrap();
+        assert_eq!(file.content_type(), ContentType::Empty);
+        assert_eq!(file.total_size(), 0x200);
+        let mut data = Vec::new();
+        assert_eq!(file.read_to_end(&mut data).unwrap(), 0x200);
+        assert!(data.iter().all(|&b| b == 0));
+
+        assert_eq!(file.seek(SeekFrom::End(-0x10)).unwrap(), 0x1F0);
+        assert_eq!(file.read_to_end(&mut data).unwrap(), 0x10);
+        assert_eq!(file.seek(SeekFrom::End(1)).unwrap(), 0x201);
+        assert_eq!(file.read_to_end(&mut data).unwrap(), 0);
+
+        dat[8..12].copy_from_slice(&[0; 4]);
+        let mut file = SqFile::open_reader(Cursor::new(dat), test_entry()).unwrap();
+        assert_eq!(file.total_size(), 0);
+        assert_eq!(file.read(&mut [0; 4]).unwrap(), 0);
+    }
+
+    #[test]
+    fn reads_padded_compressed_blocks() {
+        let blocks: [&[u8]; 2] = [&[b'a'; 300], b"compressed tail"];
+        let mut data = Vec::new();
+        let mut table = Vec::new();
+        for block in blocks {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(block).unwrap();
+            let compressed = encoder.finish().unwrap();
+
+            let offset = data.len();
+            data.write_u32::<LE>(0x10).unwrap();
+            data.write_u32::<LE>(0).unwrap();
+            data.write_u32::<LE>(compressed.len() as u32).unwrap();
+            data.write_u32::<LE>(block.len() as u32).unwrap();
+            data.extend_from_slice(&compressed);
+            // blocks are padded up to 0x80 bytes, including the last one of the .dat file
+            data.resize((data.len() + 0x7f) & !0x7f, 0);
+            table.push((
+                offset as u32,
+                (data.len() - offset) as u16,
+                block.len() as u16,
+            ));
+        }
+
+        let mut dat = Vec::new();
+        dat.write_u32::<LE>(0x80).unwrap();
+        dat.write_u32::<LE>(2).unwrap();
+        dat.write_u32::<LE>(blocks.iter().map(|b| b.len() as u32).sum())
+            .unwrap();
+        dat.write_u32::<LE>(0).unwrap();
+        dat.write_u32::<LE>(0).unwrap();
+        dat.write_u32::<LE>(blocks.len() as u32).unwrap();
+        for (offset, size, decompressed_size) in table {
+            dat.write_u32::<LE>(offset).unwrap();
+            dat.write_u16::<LE>(size).unwrap();
+            dat.write_u16::<LE>(decompressed_size).unwrap();
+        }
+        dat.resize(0x80, 0);
+        dat.extend_from_slice(&data);
+
+        let mut file = SqFile::open_reader(Cursor::new(dat), test_entry()).unwrap();
+        let mut read = Vec::new();
+        file.read_to_end(&mut read).unwrap();
+        assert_eq!(read, blocks.concat());
+
+        file.seek(SeekFrom::Start(298)).unwrap();
+        assert_eq!(read_n(&mut file, 6), b"aacomp");
+    }
+
+    #[test]
+    fn big_endian_entry() {
+        let data = b"big endian data";
+        let mut dat = Vec::new();
+        dat.write_u32::<BE>(0x80).unwrap();
+        dat.write_u32::<BE>(2).unwrap();
+        dat.write_u32::<BE>(data.len() as u32).unwrap();
+        dat.write_u32::<BE>(0).unwrap();
+        dat.write_u32::<BE>(0).unwrap();
+        dat.write_u32::<BE>(1).unwrap();
+        dat.write_u32::<BE>(0).unwrap();
+        dat.write_u16::<BE>(0x80).unwrap();
+        dat.write_u16::<BE>(data.len() as u16).unwrap();
+        dat.resize(0x80, 0);
+        dat.write_u32::<BE>(0x10).unwrap();
+        dat.write_u32::<BE>(0).unwrap();
+        dat.write_u32::<BE>(32000).unwrap();
+        dat.write_u32::<BE>(data.len() as u32).unwrap();
+        dat.extend_from_slice(data);
+
+        let mut file =
+            SqFile::open_reader_for(Cursor::new(dat), test_entry(), Platform::PS3).unwrap();
+        assert_eq!(file.content_type(), ContentType::Binary);
+        let mut read = Vec::new();
+        file.read_to_end(&mut read).unwrap();
+        assert_eq!(read, data);
+    }
+}
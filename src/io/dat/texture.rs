@@ -0,0 +1,206 @@
+use crate::{
+    error::SqResult,
+    io::{
+        dat::sqfile::{add_block_size, data_position, BlockTableEntry, DatInfo, FileLayout},
+        index::IndexFileEntry,
+    },
+};
+use std::io::{Read, Seek, SeekFrom};
+
+/// The offset of the LOD block table relative to the start of the data header
+const LOD_TABLE_OFFSET: u64 = 0x18;
+
+/// Information about a single mipmap level of a texture, stored in the data header
+struct LodBlock {
+    /// Offset of the first block relative to the end of the data header
+    compressed_offset: u32,
+    /// The total size of the blocks of this level
+    compressed_size: u32,
+    /// The size of this level once decompressed
+    decompressed_size: u32,
+    /// The index of the first block of this level within the sub-block size table
+    block_offset: u32,
+    /// The number of blocks this level is split into
+    block_count: u32,
+}
+
+/// Reads the layout of a Texture file. The raw .tex header is stored uncompressed before the
+/// blocks, and is read into memory as the header of the layout.
+pub(super) fn read_texture_layout<R>(
+    reader: &mut R,
+    index_entry: &IndexFileEntry,
+    dat_info: &DatInfo,
+) -> SqResult<FileLayout>
+where
+    R: Read + Seek,
+{
+    reader.seek(SeekFrom::Start(data_position(
+        index_entry,
+        LOD_TABLE_OFFSET,
+    )?))?;
+    let endian = dat_info.endian;
+    let mut lods = Vec::with_capacity(dat_info.blocks_len as usize);
+    for _ in 0..dat_info.blocks_len {
+        let compressed_offset = endian.read_u32(reader)?;
+        let compressed_size = endian.read_u32(reader)?;
+        let decompressed_size = endian.read_u32(reader)?;
+        let block_offset = endian.read_u32(reader)?;
+        let block_count = endian.read_u32(reader)?;
+        lods.push(LodBlock {
+            compressed_offset,
+            compressed_size,
+            decompressed_size,
+            block_offset,
+            block_count,
+        });
+    }
+
+    // the sub-block size table follows the LOD table, and each level names the range of it that
+    // holds the sizes of its blocks
+    let table_len = lods
+        .iter()
+        .map(|lod| lod.block_offset as u64 + lod.block_count as u64)
+        .max()
+        .unwrap_or(0);
+    let mut sizes = Vec::new();
+    for _ in 0..table_len {
+        sizes.push(endian.read_u16(reader)?);
+    }
+
+    // the decompressed sizes of individual blocks are only found in their block headers, so they
+    // are read once they are needed
+    let mut blocks = Vec::new();
+    let mut total_size = 0;
+    for lod in lods.iter().filter(|lod| lod.compressed_size != 0) {
+        let start = lod.block_offset as usize;
+        let lod_sizes = &sizes[start..start + lod.block_count as usize];
+        let mut offset = lod.compressed_offset;
+        for &block_size in lod_sizes {
+            blocks.push(BlockTableEntry {
+                offset,
+                block_size,
+                decompressed_size: None,
+            });
+            offset = add_block_size(offset, block_size as u32)?;
+        }
+        total_size += lod.decompressed_size as usize;
+    }
+
+    // the .tex header sits between the data header and the first level
+    let header_size = lods.first().map(|lod| lod.compressed_offset).unwrap_or(0);
+    reader.seek(SeekFrom::Start(data_position(
+        index_entry,
+        dat_info.header_len as u64,
+    )?))?;
+    let mut header = crate::buffer(header_size as usize);
+    reader.read_exact(&mut header)?;
+
+    Ok(FileLayout {
+        total_size: header.len() + total_size,
+        header,
+        blocks,
+    })
+}
+
+#[cfg(test)]
+mod texture_tests {
+    use crate::io::dat::{
+        sqfile::{test_entry, write_uncompressed_block},
+        ContentType, SqFile,
+    };
+    use byteorder::{WriteBytesExt, LE};
+    use std::io::{Cursor, Read, Seek, SeekFrom};
+
+    /// Builds a Texture entry with `levels` of uncompressed blocks, placing the sizes of each
+    /// level's blocks at its entry of `block_offsets` in the sub-block size table.
+    fn texture_dat(tex_header: &[u8], levels: &[&[&[u8]]], block_offsets: &[u32]) -> Vec<u8> {
+        let header_len = 0x80u32;
+        let mut data = tex_header.to_vec();
+        let mut lod_table = Vec::new();
+        let mut sizes = Vec::new();
+        for (level, &block_offset) in levels.iter().zip(block_offsets) {
+            let offset = data.len() as u32;
+            let mut decompressed_size = 0;
+            let mut level_sizes = Vec::new();
+            for block in level.iter() {
+                level_sizes.push(write_uncompressed_block(&mut data, block));
+                decompressed_size += block.len() as u32;
+            }
+            let start = block_offset as usize;
+            if sizes.len() < start + level_sizes.len() {
+                sizes.resize(start + level_sizes.len(), 0);
+            }
+            sizes[start..start + level_sizes.len()].copy_from_slice(&level_sizes);
+            lod_table.push([
+                offset,
+                data.len() as u32 - offset,
+                decompressed_size,
+                block_offset,
+                level.len() as u32,
+            ]);
+        }
+
+        let mut dat = Vec::new();
+        dat.write_u32::<LE>(header_len).unwrap();
+        dat.write_u32::<LE>(4).unwrap();
+        dat.write_u32::<LE>(0).unwrap();
+        dat.write_u32::<LE>(0).unwrap();
+        dat.write_u32::<LE>(0).unwrap();
+        dat.write_u32::<LE>(levels.len() as u32).unwrap();
+        lod_table
+            .iter()
+            .flatten()
+            .for_each(|&v| dat.write_u32::<LE>(v).unwrap());
+        sizes.iter().for_each(|&v| dat.write_u16::<LE>(v).unwrap());
+        dat.resize(header_len as usize, 0);
+        dat.extend_from_slice(&data);
+        dat
+    }
+
+    fn read_tex(dat: Vec<u8>) -> Vec<u8> {
+        let mut file = SqFile::open_reader(Cursor::new(dat), test_entry()).unwrap();
+        assert_eq!(file.content_type(), ContentType::Texture);
+        let mut tex = Vec::new();
+        file.read_to_end(&mut tex).unwrap();
+        assert_eq!(tex.len(), file.total_size());
+        tex
+    }
+
+    #[test]
+    fn reconstructs_tex() {
+        let tex_header = [0xAAu8; 0x50];
+        let levels: [&[&[u8]]; 2] = [&[b"mip0 block0", b"mip0 block1"], &[b"mip1"]];
+        let tex = read_tex(texture_dat(&tex_header, &levels, &[0, 2]));
+        assert_eq!(&tex[..0x50], tex_header.as_ref());
+        assert_eq!(&tex[0x50..], b"mip0 block0mip0 block1mip1");
+    }
+
+    #[test]
+    fn seeks_into_unread_blocks() {
+        let tex_header = [0xAAu8; 0x50];
+        let levels: [&[&[u8]]; 2] = [&[b"mip0 block0", b"mip0 block1"], &[b"mip1"]];
+        let dat = texture_dat(&tex_header, &levels, &[0, 2]);
+        let mut file = SqFile::open_reader(Cursor::new(dat), test_entry()).unwrap();
+
+        let mut data = [0; 6];
+        file.seek(SeekFrom::Start(0x50 + 16)).unwrap();
+        file.read_exact(&mut data).unwrap();
+        assert_eq!(&data, b"block1");
+        file.seek(SeekFrom::End(-6)).unwrap();
+        file.read_exact(&mut data).unwrap();
+        assert_eq!(&data, b"k1mip1");
+        file.seek(SeekFrom::Start(0x4E)).unwrap();
+        file.read_exact(&mut data).unwrap();
+        assert_eq!(&data, b"\xAA\xAAmip0");
+    }
+
+    #[test]
+    fn reads_block_sizes_by_offset() {
+        // the sizes of the last level come first in the table, and the empty level between them
+        // has no sizes at all
+        let tex_header = [0xAAu8; 0x50];
+        let levels: [&[&[u8]]; 3] = [&[b"mip0 block0", b"mip0 block1"], &[], &[b"mip2"]];
+        let tex = read_tex(texture_dat(&tex_header, &levels, &[1, 3, 0]));
+        assert_eq!(&tex[0x50..], b"mip0 block0mip0 block1mip2");
+    }
+}
@@ -0,0 +1,93 @@
+use crate::sqpath::Platform;
+use byteorder::{ReadBytesExt, WriteBytesExt, BE, LE};
+use std::io::{Read, Result as IOResult, Write};
+
+/// The byte order of the values within SqPack files, which depends on the platform the files
+/// were built for.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub(crate) enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+impl Endian {
+    /// Returns the byte order of files built for `platform`.
+    pub fn of(platform: Platform) -> Endian {
+        if platform.is_big_endian() {
+            Endian::Big
+        } else {
+            Endian::Little
+        }
+    }
+
+    /// Reads a u16 in this byte order.
+    pub fn read_u16<R: Read + ?Sized>(self, reader: &mut R) -> IOResult<u16> {
+        match self {
+            Endian::Little => reader.read_u16::<LE>(),
+            Endian::Big => reader.read_u16::<BE>(),
+        }
+    }
+
+    /// Reads a u32 in this byte order.
+    pub fn read_u32<R: Read + ?Sized>(self, reader: &mut R) -> IOResult<u32> {
+        match self {
+            Endian::Little => reader.read_u32::<LE>(),
+            Endian::Big => reader.read_u32::<BE>(),
+        }
+    }
+
+    /// Reads enough u16s in this byte order to fill `dst`.
+    pub fn read_u16_into<R: Read + ?Sized>(self, reader: &mut R, dst: &mut [u16]) -> IOResult<()> {
+        match self {
+            Endian::Little => reader.read_u16_into::<LE>(dst),
+            Endian::Big => reader.read_u16_into::<BE>(dst),
+        }
+    }
+
+    /// Reads enough u32s in this byte order to fill `dst`.
+    pub fn read_u32_into<R: Read + ?Sized>(self, reader: &mut R, dst: &mut [u32]) -> IOResult<()> {
+        match self {
+            Endian::Little => reader.read_u32_into::<LE>(dst),
+            Endian::Big => reader.read_u32_into::<BE>(dst),
+        }
+    }
+
+    /// Writes a u16 in this byte order.
+    pub fn write_u16<W: Write + ?Sized>(self, writer: &mut W, value: u16) -> IOResult<()> {
+        match self {
+            Endian::Little => writer.write_u16::<LE>(value),
+            Endian::Big => writer.write_u16::<BE>(value),
+        }
+    }
+
+    /// Writes a u32 in this byte order.
+    pub fn write_u32<W: Write + ?Sized>(self, writer: &mut W, value: u32) -> IOResult<()> {
+        match self {
+            Endian::Little => writer.write_u32::<LE>(value),
+            Endian::Big => writer.write_u32::<BE>(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod endian_tests {
+    use crate::{io::endian::Endian, sqpath::Platform};
+    use std::io::Cursor;
+
+    #[test]
+    fn platform_byte_order() {
+        assert_eq!(Endian::of(Platform::Win32), Endian::Little);
+        assert_eq!(Endian::of(Platform::PS3), Endian::Big);
+
+        let data = [0x12, 0x34, 0x56, 0x78];
+        assert_eq!(
+            Endian::Little.read_u32(&mut Cursor::new(data)).unwrap(),
+            0x78563412
+        );
+        assert_eq!(
+            Endian::Big.read_u32(&mut Cursor::new(data)).unwrap(),
+            0x12345678
+        );
+    }
+}
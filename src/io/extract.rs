@@ -0,0 +1,429 @@
+use crate::{
+    error::{SqResult, SqpackError},
+    io::{
+        dat::{sqfile::dat_path, SharedDat, SqFile},
+        index::{IndexCache, IndexFileEntry, IndexReader, PathDictionary},
+    },
+    open_cached,
+    sqpath::{Expansion, FileType, Platform},
+};
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Selects which index files of a SqPack are extracted. Fields left as `None` match every index
+/// file.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub struct ExtractFilter {
+    /// Only extract index files of this category
+    pub file_type: Option<FileType>,
+
+    /// Only extract index files of this expansion
+    pub expansion: Option<Expansion>,
+}
+
+impl ExtractFilter {
+    /// Checks whether the index file named `file_name`, such as `0c0100.win32.index`, is selected
+    /// by this filter and is built for `platform`.
+    pub fn matches(&self, file_name: &str, platform: Platform) -> bool {
+        let suffix = format!(".{}.index", platform.as_str());
+        let Some(stem) = file_name.strip_suffix(&suffix) else {
+            return false;
+        };
+        let byte = |i: usize| {
+            stem.get(i..i + 2)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        };
+        match (stem.len(), byte(0), byte(2), byte(4)) {
+            (6, Some(file_type), Some(expansion), Some(_)) => {
+                self.file_type
+                    .is_none_or(|ft| ft.file_name_prefix() == file_type)
+                    && self.expansion.is_none_or(|exp| exp.number() == expansion)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A file that was read from a SqPack during extraction
+#[derive(Clone, Debug)]
+pub struct ExtractedFile<'a> {
+    /// The path of the index file the entry was read from
+    pub index_path: &'a Path,
+
+    /// The index entry of the file
+    pub entry: IndexFileEntry,
+
+    /// The decompressed contents of the file
+    pub data: Vec<u8>,
+}
+
+impl ExtractedFile<'_> {
+    /// A relative path to store the file at when its real path is unknown, made from the name of
+    /// its index file and the hashes of its path, such as `0c0000/0af269d6/e3b71579`.
+    pub fn hashed_path(&self) -> PathBuf {
+        let index_name = self
+            .index_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.split('.').next())
+            .unwrap_or_default();
+        let hash = self.entry.path_hash;
+        Path::new(index_name)
+            .join(format!("{:08x}", hash.folder_hash))
+            .join(format!("{:08x}", hash.file_hash))
+    }
+
+    /// A relative path to store the file at, which is its real path if `dictionary` knows it and
+    /// its [`hashed_path`](#method.hashed_path) otherwise.
+    pub fn resolved_path(&self, dictionary: &PathDictionary) -> PathBuf {
+        match dictionary.resolve(&self.entry) {
+            Some(sqpath) => PathBuf::from(sqpath.as_str()),
+            None => self.hashed_path(),
+        }
+    }
+}
+
+/// A file that could not be extracted
+#[derive(Debug)]
+pub struct ExtractFailure {
+    /// The path of the index file the entry was read from
+    pub index_path: PathBuf,
+
+    /// The index entry of the file
+    pub entry: IndexFileEntry,
+
+    /// Why the file could not be read or stored
+    pub error: SqpackError,
+}
+
+/// The progress of an extraction, reported after each file
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct ExtractProgress {
+    /// The number of files that were extracted
+    pub extracted: usize,
+
+    /// The number of files that failed to extract
+    pub failed: usize,
+
+    /// The number of files being extracted in total
+    pub total: usize,
+}
+
+/// The outcome of an extraction
+#[derive(Debug, Default)]
+pub struct ExtractReport {
+    /// The number of files that were extracted
+    pub extracted: usize,
+
+    /// The files that failed to extract, in no particular order
+    pub failures: Vec<ExtractFailure>,
+}
+
+impl ExtractReport {
+    /// Returns true if every file was extracted.
+    pub fn is_ok(&self) -> bool { self.failures.is_empty() }
+}
+
+/// Extracts every file of the index file at `index_path` in parallel, passing each to `sink`.
+/// Failing to read or store a file does not stop the extraction; the failure is recorded in the
+/// returned report instead. `progress` is called after every file with the counts as they were once
+/// it finished. It is called from the threads doing the extraction, so calls may overlap and arrive
+/// out of order.
+///
+/// # Examples
+/// ```no_run
+/// use sqpack::io::extract::extract_all;
+///
+/// let index_path = "/home/uwu/ffxiv/game/sqpack/ffxiv/0a0000.win32.index";
+/// let report = extract_all(
+///     index_path,
+///     |file| {
+///         println!("{:?}: {} bytes", file.entry.path_hash, file.data.len());
+///         Ok(())
+///     },
+///     |progress| eprintln!("{}/{}", progress.extracted + progress.failed, progress.total),
+/// )
+/// .unwrap();
+/// assert!(report.is_ok());
+/// ```
+pub fn extract_all<P, S, F>(index_path: P, sink: S, progress: F) -> SqResult<ExtractReport>
+where
+    P: AsRef<Path>,
+    S: Fn(ExtractedFile) -> SqResult<()> + Sync,
+    F: Fn(ExtractProgress) + Sync,
+{
+    let index_path = index_path.as_ref();
+    let mut reader = IndexReader::new(fs::File::open(index_path)?)?;
+    let cache = IndexCache::from_reader(&mut reader)?;
+    let dats = Mutex::new(HashMap::new());
+    Ok(extract_entries(
+        &[(index_path, &cache)],
+        |path| open_cached(&dats, path, |path| SharedDat::open(path)),
+        sink,
+        progress,
+    ))
+}
+
+/// Extracts every file of the index file at `index_path` into `out_dir`, at the paths given by
+/// [`ExtractedFile::hashed_path`](struct.ExtractedFile.html#method.hashed_path). See
+/// [`extract_all`](fn.extract_all.html).
+pub fn extract_all_to_dir<P, D, F>(
+    index_path: P,
+    out_dir: D,
+    progress: F,
+) -> SqResult<ExtractReport>
+where
+    P: AsRef<Path>,
+    D: AsRef<Path>,
+    F: Fn(ExtractProgress) + Sync,
+{
+    extract_all(index_path, dir_sink(out_dir.as_ref()), progress)
+}
+
+/// Creates a sink that writes files into `out_dir` at their hashed paths.
+pub(crate) fn dir_sink(out_dir: &Path) -> impl Fn(ExtractedFile) -> SqResult<()> + Sync + '_ {
+    move |file| {
+        let path = out_dir.join(file.hashed_path());
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, &file.data)?;
+        Ok(())
+    }
+}
+
+/// Extracts every file of the cached `indexes` in parallel. The .dat files are opened through
+/// `open_dat` given their path.
+pub(crate) fn extract_entries<O, S, F>(
+    indexes: &[(&Path, &IndexCache)],
+    open_dat: O,
+    sink: S,
+    progress: F,
+) -> ExtractReport
+where
+    O: Fn(&Path) -> SqResult<SharedDat> + Sync,
+    S: Fn(ExtractedFile) -> SqResult<()> + Sync,
+    F: Fn(ExtractProgress) + Sync,
+{
+    let entries = indexes
+        .iter()
+        .flat_map(|(index_path, cache)| cache.files().map(move |entry| (*index_path, *entry)))
+        .collect::<Vec<_>>();
+    let total = entries.len();
+    // the counts are updated under a lock, so every report is distinct, but they are reported after
+    // it is released so that `progress` never holds up the other threads
+    let counts = Mutex::new(ExtractProgress {
+        extracted: 0,
+        failed: 0,
+        total,
+    });
+
+    let failures = entries
+        .into_par_iter()
+        .filter_map(|(index_path, entry)| {
+            let result = read_entry(index_path, entry, &open_dat).and_then(|data| {
+                sink(ExtractedFile {
+                    index_path,
+                    entry,
+                    data,
+                })
+            });
+            let failure = result.err().map(|error| ExtractFailure {
+                index_path: index_path.to_path_buf(),
+                entry,
+                error,
+            });
+            let snapshot = {
+                let mut counts = counts.lock().unwrap();
+                match failure {
+                    Some(_) => counts.failed += 1,
+                    None => counts.extracted += 1,
+                }
+                *counts
+            };
+            progress(snapshot);
+            failure
+        })
+        .collect();
+
+    ExtractReport {
+        extracted: counts.into_inner().unwrap().extracted,
+        failures,
+    }
+}
+
+/// Reads the whole file described by `entry` of the index file at `index_path`.
+fn read_entry<O>(index_path: &Path, entry: IndexFileEntry, open_dat: &O) -> SqResult<Vec<u8>>
+where
+    O: Fn(&Path) -> SqResult<SharedDat>,
+{
+    let dat = open_dat(&dat_path(index_path, entry.dat_file)?)?;
+    let mut file = SqFile::open_shared(&dat, entry)?;
+    let mut data = Vec::with_capacity(file.total_size());
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod extract_tests {
+    use crate::{
+        io::{
+            extract::{extract_all, extract_all_to_dir, ExtractFilter},
+            index::PathDictionary,
+        },
+        sqpath::{Expansion, FileType, Platform},
+        test_util::TempSqPack,
+        SqPack,
+    };
+    use std::{path::PathBuf, sync::Mutex};
+
+    #[test]
+    fn filter_matches_index_names() {
+        let music = ExtractFilter {
+            file_type: Some(FileType::Music),
+            expansion: None,
+        };
+        assert!(music.matches("0c0000.win32.index", Platform::Win32));
+        assert!(music.matches("0c0300.win32.index", Platform::Win32));
+        assert!(!music.matches("0a0000.win32.index", Platform::Win32));
+        assert!(!music.matches("0c0000.ps3.index", Platform::Win32));
+        assert!(!music.matches("0c0000.win32.index2", Platform::Win32));
+        assert!(!music.matches("0c0000.win32.dat0", Platform::Win32));
+
+        let ex1 = ExtractFilter {
+            file_type: None,
+            expansion: Some(Expansion::Heavensward),
+        };
+        assert!(ex1.matches("020101.ps4.index", Platform::PS4));
+        assert!(!ex1.matches("020201.ps4.index", Platform::PS4));
+    }
+
+    /// Writes twenty music files of increasing size into a fresh sqpack
+    fn music_pack(name: &str) -> (TempSqPack, PathBuf) {
+        let files = (0..20u32)
+            .map(|i| {
+                let data = (0..i * 500).map(|b| b as u8).collect::<Vec<_>>();
+                (format!("music/ffxiv/bgm_{}.scd", i), data)
+            })
+            .collect::<Vec<_>>();
+        let temp = TempSqPack::new(name);
+        let index_path = temp.write("ffxiv/0c0000.win32.index", &files);
+        (temp, index_path)
+    }
+
+    #[test]
+    fn extract_all_reports_failures_and_progress() {
+        let (_temp, index_path) = music_pack("extract-failures");
+
+        let sizes = Mutex::new(Vec::new());
+        let reports = Mutex::new(Vec::new());
+        let report = extract_all(
+            &index_path,
+            |file| {
+                if file.data.len() == 1500 {
+                    return Err(crate::error::SqpackError::SqFileNotFound);
+                }
+                sizes.lock().unwrap().push(file.data.len());
+                Ok(())
+            },
+            |progress| {
+                assert_eq!(progress.total, 20);
+                reports
+                    .lock()
+                    .unwrap()
+                    .push(progress.extracted + progress.failed);
+            },
+        )
+        .unwrap();
+
+        assert_eq!(report.extracted, 19);
+        assert_eq!(report.failures.len(), 1);
+        let mut reports = reports.into_inner().unwrap();
+        reports.sort_unstable();
+        assert_eq!(reports, (1..=20).collect::<Vec<_>>());
+        let mut sizes = sizes.into_inner().unwrap();
+        sizes.sort_unstable();
+        assert_eq!(sizes.len(), 19);
+        assert_eq!(sizes[19 - 1], 19 * 500);
+    }
+
+    #[test]
+    fn extract_all_to_dir_writes_every_file() {
+        let (temp, index_path) = music_pack("extract-dir");
+
+        let out_dir = temp.root().join("out");
+        let report = extract_all_to_dir(&index_path, &out_dir, |_| {}).unwrap();
+        assert!(report.is_ok());
+        let extracted = walkdir::WalkDir::new(out_dir.join("0c0000"))
+            .into_iter()
+            .filter(|entry| entry.as_ref().unwrap().file_type().is_file())
+            .count();
+        assert_eq!(extracted, 20);
+    }
+
+    #[test]
+    fn extracted_files_resolve_known_paths() {
+        let (_temp, index_path) = music_pack("extract-resolve");
+
+        let dictionary = ["music/ffxiv/bgm_3.scd"]
+            .into_iter()
+            .collect::<PathDictionary>();
+        let named = Mutex::new(Vec::new());
+        let report = extract_all(
+            &index_path,
+            |file| {
+                let path = file.resolved_path(&dictionary);
+                if path != file.hashed_path() {
+                    named.lock().unwrap().push(path);
+                }
+                Ok(())
+            },
+            |_| {},
+        )
+        .unwrap();
+        assert!(report.is_ok());
+        assert_eq!(
+            named.into_inner().unwrap(),
+            [PathBuf::from("music/ffxiv/bgm_3.scd")]
+        );
+    }
+
+    #[test]
+    fn sqpack_extract_all_filters_indexes() {
+        let (temp, _) = music_pack("extract-filter");
+        temp.write("ffxiv/0a0000.win32.index", &[("exd/root.exl", b"EXLT")]);
+
+        let sqpack = SqPack::new(temp.root());
+        let filter = ExtractFilter {
+            file_type: Some(FileType::EXD),
+            ..ExtractFilter::default()
+        };
+        let files = Mutex::new(Vec::new());
+        let report = sqpack
+            .extract_all(
+                filter,
+                |file| {
+                    files.lock().unwrap().push(file.hashed_path());
+                    Ok(())
+                },
+                |_| {},
+            )
+            .unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.extracted, 1);
+        assert!(files.into_inner().unwrap()[0].starts_with("0a0000"));
+        assert_eq!(
+            sqpack
+                .extract_all(ExtractFilter::default(), |_| Ok(()), |_| {})
+                .unwrap()
+                .extracted,
+            21
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{
+    error::{SqResult, SqpackError},
+    io::endian::Endian,
+    sqpath::Platform,
+};
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+/// The expected signature of SqPack Files
+pub(crate) const SQPACK_SIGNATURE: [u8; 6] = [0x53, 0x71, 0x50, 0x61, 0x63, 0x6b];
+
+/// The size of the SqPack header written by the game
+pub(crate) const SQPACK_HEADER_SIZE: usize = 0x400;
+
+/// The offset of the SHA-1 hash of the header, relative to the start of the header
+pub(crate) const SQPACK_HEADER_SHA1_OFFSET: u64 = 0x3C0;
+
+/// The type of data a SqPack file contains, as stored in its header
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum SqPackFileType {
+    /// A .dat file, containing the data of files
+    Dat,
+    /// An .index or .index2 file, used to locate files within the .dat files
+    Index,
+    /// A file type unknown to this crate
+    Unknown(u32),
+}
+
+impl SqPackFileType {
+    /// Returns the type ID as stored in the SqPack header.
+    pub fn id(&self) -> u32 {
+        match self {
+            SqPackFileType::Dat => 1,
+            SqPackFileType::Index => 2,
+            SqPackFileType::Unknown(id) => *id,
+        }
+    }
+}
+
+impl From<u32> for SqPackFileType {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => SqPackFileType::Dat,
+            2 => SqPackFileType::Index,
+            unk => SqPackFileType::Unknown(unk),
+        }
+    }
+}
+
+/// The header found at the start of every SqPack index and .dat file.
+///
+/// # Examples
+/// ```no_run
+/// use sqpack::io::header::{SqPackFileType, SqPackHeader};
+/// use std::fs::File;
+///
+/// let mut file = File::open("/home/uwu/ffxiv/game/sqpack/ffxiv/0c0000.win32.index").unwrap();
+/// let header = SqPackHeader::read(&mut file).unwrap();
+/// assert_eq!(header.file_type, SqPackFileType::Index);
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct SqPackHeader {
+    /// The ID of the platform the file was built for, see [`platform`](#method.platform)
+    pub platform_id: u8,
+
+    /// The size of this header, the following segment header starts here
+    pub header_size: u32,
+
+    /// The version of the SqPack format
+    pub version: u32,
+
+    /// Whether this is an index or .dat file
+    pub file_type: SqPackFileType,
+
+    /// The date the file was built, as stored by the game
+    pub build_date: u32,
+
+    /// The time the file was built, as stored by the game
+    pub build_time: u32,
+
+    /// The SHA-1 hash of the header, stored at the end of the header
+    pub sha1: [u8; 20],
+}
+
+impl SqPackHeader {
+    /// Creates the header of a new file of type `file_type`, with the header size and version
+    /// used by the game. The hash is left empty.
+    pub fn new(file_type: SqPackFileType) -> SqPackHeader {
+        SqPackHeader {
+            platform_id: 0,
+            header_size: SQPACK_HEADER_SIZE as u32,
+            version: 1,
+            file_type,
+            build_date: 0,
+            build_time: 0,
+            sha1: [0; 20],
+        }
+    }
+
+    /// Gets the platform the file was built for, which determines the byte order of the file.
+    ///
+    /// # Returns
+    /// `Err(SqpackError::UnknownPlatform)` if the platform ID is not known to this crate.
+    pub fn platform(&self) -> SqResult<Platform> {
+        Platform::from_id(self.platform_id).ok_or(SqpackError::UnknownPlatform(self.platform_id))
+    }
+
+    /// The byte order of the file, assuming little-endian for unknown platforms
+    pub(crate) fn endian(&self) -> Endian {
+        Platform::from_id(self.platform_id)
+            .map(Endian::of)
+            .unwrap_or_default()
+    }
+
+    /// Reads the header from the start of `reader`. The reader position is not guaranteed to be
+    /// the same after calling.
+    ///
+    /// # Returns
+    /// `Err(SqpackError::NotSqPack)` if the reader does not start with the SqPack signature.
+    pub fn read<R: Read + Seek>(reader: &mut R) -> SqResult<SqPackHeader> {
+        reader.seek(SeekFrom::Start(0))?;
+        let mut signature = [0; 8];
+        reader.read_exact(&mut signature)?;
+        if signature[..SQPACK_SIGNATURE.len()] != SQPACK_SIGNATURE {
+            return Err(SqpackError::NotSqPack);
+        }
+        let platform_id = reader.read_u8()?;
+        let endian = Platform::from_id(platform_id)
+            .map(Endian::of)
+            .unwrap_or_default();
+        reader.seek(SeekFrom::Current(3))?;
+        let header_size = endian.read_u32(reader)?;
+        let version = endian.read_u32(reader)?;
+        let file_type = SqPackFileType::from(endian.read_u32(reader)?);
+        let build_date = endian.read_u32(reader)?;
+        let build_time = endian.read_u32(reader)?;
+
+        reader.seek(SeekFrom::Start(SQPACK_HEADER_SHA1_OFFSET))?;
+        let mut sha1 = [0; 20];
+        reader.read_exact(&mut sha1)?;
+
+        Ok(SqPackHeader {
+            platform_id,
+            header_size,
+            version,
+            file_type,
+            build_date,
+            build_time,
+            sha1,
+        })
+    }
+
+    /// Serializes the header to the `SQPACK_HEADER_SIZE` bytes written at the start of a file.
+    pub(crate) fn to_bytes(self) -> SqResult<Box<[u8]>> {
+        let endian = self.endian();
+        let mut header = Cursor::new(crate::buffer(SQPACK_HEADER_SIZE));
+        header.write_all(&SQPACK_SIGNATURE)?;
+        header.seek(SeekFrom::Start(0x8))?;
+        header.write_u8(self.platform_id)?;
+        header.seek(SeekFrom::Start(0xC))?;
+        endian.write_u32(&mut header, self.header_size)?;
+        endian.write_u32(&mut header, self.version)?;
+        endian.write_u32(&mut header, self.file_type.id())?;
+        endian.write_u32(&mut header, self.build_date)?;
+        endian.write_u32(&mut header, self.build_time)?;
+        header.seek(SeekFrom::Start(SQPACK_HEADER_SHA1_OFFSET))?;
+        header.write_all(&self.sha1)?;
+        Ok(header.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod header_tests {
+    use crate::{
+        error::SqpackError,
+        io::header::{SqPackFileType, SqPackHeader},
+        sqpath::Platform,
+    };
+    use std::io::Cursor;
+
+    #[test]
+    fn round_trip() {
+        let header = SqPackHeader {
+            platform_id: 1,
+            build_date: 20240702,
+            build_time: 1234,
+            sha1: [7; 20],
+            ..SqPackHeader::new(SqPackFileType::Dat)
+        };
+        let bytes = header.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 0x400);
+        assert_eq!(&bytes[..6], b"SqPack");
+        let read = SqPackHeader::read(&mut Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(read, header);
+        assert_eq!(read.file_type.id(), 1);
+        assert_eq!(read.platform().unwrap(), Platform::PS3);
+        // PS3 files are big-endian
+        assert_eq!(&bytes[0xC..0x10], &[0, 0, 4, 0]);
+    }
+
+    #[test]
+    fn rejects_other_data() {
+        let mut data = Cursor::new(vec![0u8; 0x400]);
+        assert!(matches!(
+            SqPackHeader::read(&mut data),
+            Err(SqpackError::NotSqPack)
+        ));
+    }
+}
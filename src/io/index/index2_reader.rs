@@ -0,0 +1,183 @@
+use crate::{
+    error::SqResult,
+    io::{
+        endian::Endian,
+        header::SqPackHeader,
+        index::{
+            reader::{
+                read_index_header, split_data_offset, verify_index, FILE_INFO_OFFSET,
+                FILE_LENGTH_OFFSET,
+            },
+            segment::{SegmentInfo, SegmentKind, DAT_COUNT_OFFSET},
+        },
+        verify::VerifyReport,
+    },
+    sqpath::Platform,
+};
+use seek_bufread::BufReader;
+use std::io::{Read, Seek, SeekFrom};
+
+/// A buffered reader that reads `.index2` files from a wrapped `Read` instance. Unlike `.index`
+/// files, these are keyed by a single hash of the full path, see
+/// [`SqPath::sq_index2_hash`](../../sqpath/struct.SqPath.html#method.sq_index2_hash).
+pub struct Index2Reader<R>
+where
+    R: Read + Seek,
+{
+    pub(self) inner: BufReader<R>,
+    header: SqPackHeader,
+    platform: Platform,
+    endian: Endian,
+}
+
+/// An iterator over the files present in the passed Index2Reader
+pub struct Index2Files<'a, R: Read + Seek> {
+    pub(self) reader: &'a mut Index2Reader<R>,
+    pub(self) count: usize,
+    pub(self) visited: usize,
+}
+
+/// A file entry within an `.index2` file. Can be used to locate the file data within the .dat
+/// files.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Index2FileEntry {
+    /// The hash of the full path of this entry
+    pub path_hash: u32,
+
+    /// Where in the .dat file the data may be found
+    pub data_offset: u64,
+
+    /// Which .dat file the file may be found in.
+    pub dat_file: u8,
+}
+
+impl<R: Read + Seek> Index2Reader<R> {
+    /// Accepts a `Read + Seek` and wraps an `Index2Reader` around it.
+    ///
+    /// # Returns
+    /// `Ok(Index2Reader)` if `inner` was a `Read` over a SqPack index file
+    /// `Err(...)` if an I/O error occurred or if `inner` was not a `Read` over a SqPack index file.
+    pub fn new(inner: R) -> SqResult<Self> { Self::with_capacity(16384, inner) }
+
+    /// Creates an `Index2Reader` with the specified capacity. See `Index2Reader::new`.
+    pub fn with_capacity(cap: usize, inner: R) -> SqResult<Self> {
+        let mut inner = BufReader::with_capacity(cap, inner);
+        let header = read_index_header(&mut inner)?;
+        let platform = header.platform()?;
+        Ok(Index2Reader {
+            inner,
+            platform,
+            endian: Endian::of(platform),
+            header,
+        })
+    }
+
+    /// Returns the SqPack header of the index file.
+    pub fn header(&self) -> &SqPackHeader { &self.header }
+
+    /// Returns the platform the index file was built for. Values are read in its byte order.
+    pub fn platform(&self) -> Platform { self.platform }
+
+    /// Reads the number of files specified by this index file
+    pub fn files_count(&mut self) -> SqResult<usize> {
+        let header_len = self.header.header_size;
+        self.inner.seek(SeekFrom::Start(
+            header_len as u64 + FILE_INFO_OFFSET + FILE_LENGTH_OFFSET,
+        ))?;
+        Ok((self.endian.read_u32(&mut self.inner)? >> 3) as usize)
+    }
+
+    /// Creates an iterator over the files present in the index.
+    pub fn files(&mut self) -> SqResult<Index2Files<'_, R>> {
+        let count = self.files_count()?;
+        self.seek_files()?;
+        Ok(Index2Files {
+            reader: self,
+            count,
+            visited: 0,
+        })
+    }
+
+    /// Seeks the reader to the files segment.
+    pub fn seek_files(&mut self) -> SqResult<()> {
+        let header_len = self.header.header_size;
+        self.inner
+            .seek(SeekFrom::Start(header_len as u64 + FILE_INFO_OFFSET))?;
+        let offset = self.endian.read_u32(&mut self.inner)?;
+        self.inner.seek(SeekFrom::Start(offset as u64))?;
+        Ok(())
+    }
+
+    /// Reads a file entry from the index file. The underlying reader must be at a file,
+    /// or you may get corrupted data. See [`seek_files`](method.seek_files.html). After execution,
+    /// the underlying cursor is at the next file, if it exists.
+    pub fn read_file_entry(&mut self) -> SqResult<Index2FileEntry> {
+        let path_hash = self.endian.read_u32(&mut self.inner)?;
+        let (dat_file, data_offset) = split_data_offset(self.endian.read_u32(&mut self.inner)?);
+        Ok(Index2FileEntry {
+            path_hash,
+            data_offset,
+            dat_file,
+        })
+    }
+
+    /// Reads the descriptor of the segment `kind` from the index header.
+    pub fn segment(&mut self, kind: SegmentKind) -> SqResult<SegmentInfo> {
+        let header_len = self.header.header_size;
+        SegmentInfo::read(&mut self.inner, header_len, kind, self.endian)
+    }
+
+    /// Reads the descriptors of every segment of the index, in the order they are described by
+    /// the index header.
+    pub fn segments(&mut self) -> SqResult<Vec<SegmentInfo>> {
+        SegmentKind::ALL
+            .iter()
+            .map(|&kind| self.segment(kind))
+            .collect()
+    }
+
+    /// Reads the number of .dat files the entries of this index point into.
+    pub fn dat_count(&mut self) -> SqResult<u32> {
+        let header_len = self.header.header_size;
+        self.inner
+            .seek(SeekFrom::Start(header_len as u64 + DAT_COUNT_OFFSET))?;
+        Ok(self.endian.read_u32(&mut self.inner)?)
+    }
+
+    /// Recomputes the SHA-1 digests of the headers and segments of the index, reporting any that
+    /// do not match the digests stored in the file.
+    pub fn verify(&mut self) -> SqResult<VerifyReport> {
+        let header_len = self.header.header_size;
+        verify_index(&mut self.inner, header_len, self.endian)
+    }
+
+    /// Searches the index for the entry with the full path hash `path_hash`.
+    ///
+    /// # Returns
+    /// `Ok(None)` if no entry in the index has the given hash.
+    pub fn find(&mut self, path_hash: u32) -> SqResult<Option<Index2FileEntry>> {
+        for file in self.files()? {
+            let file = file?;
+            if file.path_hash == path_hash {
+                return Ok(Some(file));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<'a, R: Read + Seek> Iterator for Index2Files<'a, R> {
+    type Item = SqResult<Index2FileEntry>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.visited < self.count {
+            self.visited += 1;
+            Some(self.reader.read_file_entry())
+        } else {
+            None
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let c = self.count - self.visited;
+        (c, Some(c))
+    }
+}
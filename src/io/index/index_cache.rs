@@ -1,4 +1,19 @@
-use crate::{error::SqResult, io::index::IndexReader, sqpath::SqIndexHash};
+#[cfg(feature = "mmap")]
+use crate::io::{
+    endian::Endian,
+    index::{
+        reader::{read_index_header, split_data_offset},
+        SegmentInfo, SegmentKind,
+    },
+    mmap::MappedFile,
+};
+use crate::{
+    error::SqResult,
+    io::index::IndexReader,
+    sqpath::{SqIndexHash, SqPath},
+};
+#[cfg(feature = "mmap")]
+use std::io::{Cursor, Error as IOError, ErrorKind};
 use std::{
     collections::HashMap,
     io::{Read, Seek},
@@ -14,6 +29,7 @@ pub struct IndexCache {
 /// A folder entry within the index cache. Files can be found within.
 #[derive(Clone, PartialEq, Debug)]
 pub struct IndexFolderEntry {
+    /// The hash of the folder name
     pub folder_hash: u32,
     pub(self) files: HashMap<u32, IndexFileEntry>,
 }
@@ -25,7 +41,7 @@ pub struct IndexFileEntry {
     pub path_hash: SqIndexHash,
 
     /// Where in the .dat file the data may be found
-    pub data_offset: u32,
+    pub data_offset: u64,
 
     /// Which .dat file the file may be found in. I.
     pub dat_file: u8,
@@ -61,4 +77,98 @@ impl IndexCache {
             folders: folder_map,
         })
     }
+
+    /// Creates a new cache for an index file that is mapped into memory. The file entries are read
+    /// straight from the mapping.
+    #[cfg(feature = "mmap")]
+    pub fn from_mapped(index: &MappedFile) -> SqResult<IndexCache> {
+        let data = index.as_slice();
+        let mut cursor = Cursor::new(data);
+        let header = read_index_header(&mut cursor)?;
+        let endian = Endian::of(header.platform()?);
+        let segment =
+            SegmentInfo::read(&mut cursor, header.header_size, SegmentKind::Files, endian)?;
+        let files = data
+            .get(segment.offset as usize..segment.offset as usize + segment.size as usize)
+            .ok_or_else(|| IOError::from(ErrorKind::UnexpectedEof))?;
+
+        let mut folders: HashMap<u32, IndexFolderEntry> = HashMap::new();
+        for mut entry in files.chunks_exact(0x10) {
+            let file_hash = endian.read_u32(&mut entry)?;
+            let folder_hash = endian.read_u32(&mut entry)?;
+            let (dat_file, data_offset) = split_data_offset(endian.read_u32(&mut entry)?);
+            folders
+                .entry(folder_hash)
+                .or_insert_with(|| IndexFolderEntry {
+                    folder_hash,
+                    files: HashMap::new(),
+                })
+                .files
+                .insert(
+                    file_hash,
+                    IndexFileEntry {
+                        path_hash: SqIndexHash {
+                            file_hash,
+                            folder_hash,
+                        },
+                        data_offset,
+                        dat_file,
+                    },
+                );
+        }
+        Ok(IndexCache { folders })
+    }
+
+    /// Finds the file entry for `sqpath` within the cache.
+    ///
+    /// # Returns
+    /// `None` if the path is not present in this index, or if it could not be hashed.
+    pub fn get<P: AsRef<SqPath>>(&self, sqpath: P) -> Option<&IndexFileEntry> {
+        sqpath
+            .as_ref()
+            .sq_index_hash()
+            .and_then(|hash| self.get_hash(hash))
+    }
+
+    /// Finds the file entry with the given hash within the cache.
+    pub fn get_hash(&self, hash: SqIndexHash) -> Option<&IndexFileEntry> {
+        self.folder(hash.folder_hash)
+            .and_then(|folder| folder.get(hash.file_hash))
+    }
+
+    /// Checks whether `sqpath` is present in this index.
+    pub fn contains<P: AsRef<SqPath>>(&self, sqpath: P) -> bool { self.get(sqpath).is_some() }
+
+    /// Finds the folder with the given folder hash within the cache.
+    pub fn folder(&self, folder_hash: u32) -> Option<&IndexFolderEntry> {
+        self.folders.get(&folder_hash)
+    }
+
+    /// Creates an iterator over the folders in this index, in no particular order.
+    pub fn folders(&self) -> impl Iterator<Item = &IndexFolderEntry> + '_ { self.folders.values() }
+
+    /// Creates an iterator over every file in this index, in no particular order.
+    pub fn files(&self) -> impl Iterator<Item = &IndexFileEntry> + '_ {
+        self.folders.values().flat_map(|folder| folder.files())
+    }
+
+    /// The number of files in this index.
+    pub fn len(&self) -> usize { self.folders.values().map(|folder| folder.len()).sum() }
+
+    /// Checks whether this index contains no files.
+    pub fn is_empty(&self) -> bool { self.folders.values().all(|folder| folder.is_empty()) }
+}
+
+impl IndexFolderEntry {
+    /// Finds the file with the given file hash within this folder.
+    pub fn get(&self, file_hash: u32) -> Option<&IndexFileEntry> { self.files.get(&file_hash) }
+
+    /// Creates an iterator over the files in this folder, in no particular order.
+    pub fn files(&self) -> impl Iterator<Item = &IndexFileEntry> + '_ { self.files.values() }
+
+    /// The number of files in this folder.
+    pub fn len(&self) -> usize { self.files.len() }
+
+    /// Checks whether this folder contains no files.
+    pub fn is_empty(&self) -> bool { self.files.is_empty() }
 }
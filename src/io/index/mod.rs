@@ -1,7 +1,13 @@
+mod index2_reader;
 mod index_cache;
-mod reader;
+mod path_dictionary;
+pub(crate) mod reader;
+mod segment;
 
 pub use self::{
+    index2_reader::{Index2FileEntry, Index2Files, Index2Reader},
     index_cache::{IndexCache, IndexFileEntry, IndexFolderEntry},
-    reader::{IndexFiles, IndexReader},
+    path_dictionary::PathDictionary,
+    reader::{IndexFiles, IndexReader, IndexSynonymEntry, IndexSynonyms},
+    segment::{SegmentInfo, SegmentKind},
 };
@@ -0,0 +1,183 @@
+use crate::{
+    error::SqResult,
+    io::index::{Index2FileEntry, IndexFileEntry},
+    sqpath::{SqIndexHash, SqPathBuf},
+    SqPath,
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+/// A collection of known file paths, used to name the entries of index files, which only store
+/// hashes of the paths. Paths are looked up by both the folder and file hash used by `.index`
+/// files and the full path hash used by `.index2` files.
+///
+/// Path lists are read in the format shared by the community, which is one path per line.
+///
+/// # Examples
+/// ```no_run
+/// use sqpack::io::index::{IndexReader, PathDictionary};
+/// use std::fs::File;
+///
+/// let dictionary = PathDictionary::open("paths.txt").unwrap();
+/// let index = File::open("/home/uwu/ffxiv/game/sqpack/ffxiv/0a0000.win32.index").unwrap();
+/// let mut reader = IndexReader::new(index).unwrap();
+/// for entry in reader.files().unwrap() {
+///     let entry = entry.unwrap();
+///     match dictionary.resolve(&entry) {
+///         Some(path) => println!("{}", path.as_str()),
+///         None => println!("unknown: {:?}", entry.path_hash),
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PathDictionary {
+    paths: Vec<SqPathBuf>,
+    index_hashes: HashMap<SqIndexHash, usize>,
+    index2_hashes: HashMap<u32, usize>,
+}
+
+impl PathDictionary {
+    /// Creates an empty dictionary.
+    pub fn new() -> PathDictionary { PathDictionary::default() }
+
+    /// Reads a path list from the file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> SqResult<PathDictionary> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a path list with one path per line. Surrounding whitespace is trimmed and blank lines
+    /// are skipped.
+    pub fn from_reader<R: BufRead>(reader: R) -> SqResult<PathDictionary> {
+        let mut dictionary = PathDictionary::new();
+        dictionary.read_paths(reader)?;
+        Ok(dictionary)
+    }
+
+    /// Reads a path list with one path per line into this dictionary. See
+    /// [`from_reader`](#method.from_reader).
+    pub fn read_paths<R: BufRead>(&mut self, reader: R) -> SqResult<()> {
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if !line.is_empty() {
+                self.insert(line);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a path to the dictionary.
+    ///
+    /// # Returns
+    /// `false` if a path with the same hashes was already known, in which case the known path is
+    /// kept.
+    pub fn insert<SQ: AsRef<SqPath>>(&mut self, sqpath: SQ) -> bool {
+        let sqpath = sqpath.as_ref();
+        let index2_hash = sqpath.sq_index2_hash();
+        let index_hash = sqpath.sq_index_hash();
+        let known_index2 = self.index2_hashes.contains_key(&index2_hash);
+        let known_index = index_hash.is_none_or(|hash| self.index_hashes.contains_key(&hash));
+        if known_index2 && known_index {
+            return false;
+        }
+
+        let id = self.paths.len();
+        self.paths.push(sqpath.to_owned());
+        self.index2_hashes.entry(index2_hash).or_insert(id);
+        if let Some(hash) = index_hash {
+            self.index_hashes.entry(hash).or_insert(id);
+        }
+        true
+    }
+
+    /// Finds the path with the folder and file hash `hash`, as stored in `.index` files.
+    pub fn get(&self, hash: SqIndexHash) -> Option<&SqPath> {
+        self.index_hashes.get(&hash).map(|&id| &*self.paths[id])
+    }
+
+    /// Finds the path with the full path hash `hash`, as stored in `.index2` files.
+    pub fn get_index2(&self, hash: u32) -> Option<&SqPath> {
+        self.index2_hashes.get(&hash).map(|&id| &*self.paths[id])
+    }
+
+    /// Finds the path of an entry of an `.index` file.
+    pub fn resolve(&self, entry: &IndexFileEntry) -> Option<&SqPath> { self.get(entry.path_hash) }
+
+    /// Finds the path of an entry of an `.index2` file.
+    pub fn resolve_index2(&self, entry: &Index2FileEntry) -> Option<&SqPath> {
+        self.get_index2(entry.path_hash)
+    }
+
+    /// Iterates over the known paths in the order they were added.
+    pub fn paths(&self) -> impl Iterator<Item = &SqPath> + '_ {
+        self.paths.iter().map(|path| &**path)
+    }
+
+    /// Returns the number of known paths.
+    pub fn len(&self) -> usize { self.paths.len() }
+
+    /// Returns true if no paths are known.
+    pub fn is_empty(&self) -> bool { self.paths.is_empty() }
+}
+
+impl<SQ: AsRef<SqPath>> Extend<SQ> for PathDictionary {
+    fn extend<I: IntoIterator<Item = SQ>>(&mut self, iter: I) {
+        for sqpath in iter {
+            self.insert(sqpath);
+        }
+    }
+}
+
+impl<SQ: AsRef<SqPath>> FromIterator<SQ> for PathDictionary {
+    fn from_iter<I: IntoIterator<Item = SQ>>(iter: I) -> Self {
+        let mut dictionary = PathDictionary::new();
+        dictionary.extend(iter);
+        dictionary
+    }
+}
+
+#[cfg(test)]
+mod path_dictionary_tests {
+    use crate::{
+        io::index::{Index2FileEntry, IndexFileEntry, PathDictionary},
+        SqPath,
+    };
+
+    #[test]
+    fn resolves_both_hashes() {
+        let list = "music/ffxiv/BGM_System_Title.scd\r\n\n  exd/root.exl  \nmusic/ffxiv/bgm_system_title.scd\n";
+        let dictionary = PathDictionary::from_reader(list.as_bytes()).unwrap();
+        assert_eq!(dictionary.len(), 2);
+
+        let title = SqPath::new("music/ffxiv/BGM_System_Title.scd");
+        let entry = IndexFileEntry {
+            path_hash: title.sq_index_hash().unwrap(),
+            data_offset: 0,
+            dat_file: 0,
+        };
+        assert_eq!(dictionary.resolve(&entry), Some(title));
+
+        let entry = Index2FileEntry {
+            path_hash: 0xE09DBB74,
+            data_offset: 0,
+            dat_file: 0,
+        };
+        assert_eq!(dictionary.resolve_index2(&entry), Some(title));
+
+        let root = SqPath::new("exd/root.exl");
+        assert_eq!(dictionary.get(root.sq_index_hash().unwrap()), Some(root));
+        assert_eq!(dictionary.get_index2(root.sq_index2_hash()), Some(root));
+        assert_eq!(dictionary.get_index2(0), None);
+
+        let dictionary = ["a/b", "nofolder"].into_iter().collect::<PathDictionary>();
+        assert_eq!(dictionary.len(), 2);
+        assert_eq!(
+            dictionary.get_index2(SqPath::new("nofolder").sq_index2_hash()),
+            Some(SqPath::new("nofolder"))
+        );
+    }
+}
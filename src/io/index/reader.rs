@@ -1,15 +1,21 @@
 use crate::{
     error::{SqResult, SqpackError},
-    io::index::IndexFileEntry,
-    sqpath::SqIndexHash,
+    io::{
+        endian::Endian,
+        header::{SqPackFileType, SqPackHeader},
+        index::{
+            segment::{SegmentInfo, SegmentKind, DAT_COUNT_OFFSET, SYNONYM_ENTRY_SIZE},
+            IndexFileEntry,
+        },
+        verify::{HashedRegion, VerifyReport},
+    },
+    sqpath::{Platform, SqIndexHash},
 };
-use byteorder::{ReadBytesExt, LE};
 use seek_bufread::BufReader;
 use std::io::{Read, Seek, SeekFrom};
 
 #[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Ord, Eq, Hash, Default)]
 struct CachedInfo {
-    header_length: Option<u32>,
     files_offset: Option<u32>,
     folders_offset: Option<u32>,
     files_length: Option<u32>,
@@ -22,6 +28,9 @@ where
     R: Read + Seek,
 {
     pub(self) inner: BufReader<R>,
+    header: SqPackHeader,
+    platform: Platform,
+    endian: Endian,
     cache: CachedInfo,
 }
 
@@ -55,20 +64,41 @@ pub struct IndexFolderContents<'a, R: Read + Seek> {
     pub(self) files_visited: u32,
 }
 
-/// The expected signature of SqPack Files
-const SQPACK_SIGNATURE: [u8; 6] = [0x53, 0x71, 0x50, 0x61, 0x63, 0x6b];
+/// An entry in the synonym segment of an index file. Files whose hashes collide with another
+/// file are listed here along with their full path, so they can be told apart.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct IndexSynonymEntry {
+    /// The hash of the path of this entry
+    pub path_hash: SqIndexHash,
 
-/// The expected type ID of SqPack index files
-const SQPACK_INDEX_TYPE: u8 = 2;
+    /// Where in the .dat file the data may be found
+    pub data_offset: u64,
+
+    /// Which .dat file the file may be found in.
+    pub dat_file: u8,
+
+    /// The index of this entry among the entries sharing its hash
+    pub synonym_index: u32,
+
+    /// The full path of the file
+    pub path: String,
+}
+
+/// An iterator over the synonym entries present in the passed IndexReader
+pub struct IndexSynonyms<'a, R: Read + Seek> {
+    pub(self) reader: &'a mut IndexReader<R>,
+    pub(self) count: usize,
+    pub(self) visited: usize,
+}
 
 /// The offset after the sqpack header to find info about the files in the index file.
-const FILE_INFO_OFFSET: u64 = 0x8;
+pub(crate) const FILE_INFO_OFFSET: u64 = 0x8;
 
 /// The offset relative to `FILE_INFO_OFFSET` to find the length of the files section
-const FILE_LENGTH_OFFSET: u64 = 0x4;
+pub(super) const FILE_LENGTH_OFFSET: u64 = 0x4;
 
 /// The offset relative to the sqpack header end to find info about the folders in the index file
-const FOLDER_INFO_OFFSET: u64 = 0xE4;
+pub(crate) const FOLDER_INFO_OFFSET: u64 = 0xE4;
 
 /// The offset relative to `FOLDER_INFO_OFFSET` to find the length of the folders section
 const FOLDER_LENGTH_OFFSET: u64 = 0x4;
@@ -84,40 +114,22 @@ impl<R: Read + Seek> IndexReader<R> {
     /// Creates and `IndexReader` with the specified capacity. See `IndexReader::new`.
     pub fn with_capacity(cap: usize, inner: R) -> SqResult<Self> {
         let mut inner = BufReader::with_capacity(cap, inner);
-        inner.seek(SeekFrom::Start(0))?;
-        let mut sq_sig_buffer = [0; 6];
-        inner.read_exact(&mut sq_sig_buffer)?;
-        if sq_sig_buffer.as_ref() == SQPACK_SIGNATURE.as_ref() {
-            inner.seek(SeekFrom::Start(0x14))?;
-            let sqtype = inner.read_u8()?;
-            if sqtype == SQPACK_INDEX_TYPE {
-                Ok(IndexReader {
-                    inner,
-                    cache: Default::default(),
-                })
-            } else {
-                Err(SqpackError::IndexReaderIsNotIndex)
-            }
-        } else {
-            Err(SqpackError::IndexReaderIsNotIndex)
-        }
+        let header = read_index_header(&mut inner)?;
+        let platform = header.platform()?;
+        Ok(IndexReader {
+            inner,
+            platform,
+            endian: Endian::of(platform),
+            header,
+            cache: Default::default(),
+        })
     }
 
-    /// Reads the header length from the internal reader. The reader position is not guaranteed to be
-    /// the same after calling.
-    ///
-    /// # Returns
-    /// The header length wrapped in a result.
-    fn header_length(&mut self) -> SqResult<u32> {
-        if let Some(len) = self.cache.header_length {
-            Ok(len)
-        } else {
-            self.inner.seek(SeekFrom::Start(0x0c))?;
-            let len = self.inner.read_u32::<LE>()?;
-            self.cache.header_length = Some(len);
-            Ok(len)
-        }
-    }
+    /// Returns the SqPack header of the index file.
+    pub fn header(&self) -> &SqPackHeader { &self.header }
+
+    /// Returns the platform the index file was built for. Values are read in its byte order.
+    pub fn platform(&self) -> Platform { self.platform }
 
     /// Reads the underlying reading for the offset in the .index file to the files section data.
     /// The reader position is not guaranteed to be the same after calling.
@@ -125,10 +137,10 @@ impl<R: Read + Seek> IndexReader<R> {
         if let Some(offset) = self.cache.files_offset {
             Ok(offset)
         } else {
-            let header_len = self.header_length()?;
+            let header_len = self.header.header_size;
             self.inner
                 .seek(SeekFrom::Start(header_len as u64 + FILE_INFO_OFFSET))?;
-            let val = self.inner.read_u32::<LE>()?;
+            let val = self.endian.read_u32(&mut self.inner)?;
             self.cache.files_offset = Some(val);
             Ok(val)
         }
@@ -140,11 +152,11 @@ impl<R: Read + Seek> IndexReader<R> {
         if let Some(len) = self.cache.files_length {
             Ok(len)
         } else {
-            let header_len = self.header_length()?;
+            let header_len = self.header.header_size;
             self.inner.seek(SeekFrom::Start(
                 header_len as u64 + FILE_INFO_OFFSET + FILE_LENGTH_OFFSET,
             ))?;
-            let length = self.inner.read_u32::<LE>()?;
+            let length = self.endian.read_u32(&mut self.inner)?;
             self.cache.files_length = Some(length);
             Ok(length)
         }
@@ -156,10 +168,10 @@ impl<R: Read + Seek> IndexReader<R> {
         if let Some(offset) = self.cache.folders_offset {
             Ok(offset)
         } else {
-            let header_len = self.header_length()?;
+            let header_len = self.header.header_size;
             self.inner
                 .seek(SeekFrom::Start(header_len as u64 + FOLDER_INFO_OFFSET))?;
-            let val = self.inner.read_u32::<LE>()?;
+            let val = self.endian.read_u32(&mut self.inner)?;
             self.cache.folders_offset = Some(val);
             Ok(val)
         }
@@ -171,11 +183,11 @@ impl<R: Read + Seek> IndexReader<R> {
         if let Some(len) = self.cache.folders_length {
             Ok(len)
         } else {
-            let header_len = self.header_length()?;
+            let header_len = self.header.header_size;
             self.inner.seek(SeekFrom::Start(
                 header_len as u64 + FOLDER_INFO_OFFSET + FOLDER_LENGTH_OFFSET,
             ))?;
-            let length = self.inner.read_u32::<LE>()?;
+            let length = self.endian.read_u32(&mut self.inner)?;
             self.cache.folders_length = Some(length);
             Ok(length)
         }
@@ -192,7 +204,7 @@ impl<R: Read + Seek> IndexReader<R> {
     }
 
     /// Creates an iterator over the files present in the index.
-    pub fn files(&mut self) -> SqResult<IndexFiles<R>> {
+    pub fn files(&mut self) -> SqResult<IndexFiles<'_, R>> {
         let count = self.files_count()?;
         self.seek_files()?;
         Ok(IndexFiles {
@@ -213,12 +225,10 @@ impl<R: Read + Seek> IndexReader<R> {
     /// or you may get corrupted data. See [`seek_files`](method.seek_files.html). After execution,
     /// the underlying cursor is at the next file, if it exists.
     pub fn read_file_entry(&mut self) -> SqResult<IndexFileEntry> {
-        let file_hash = self.inner.read_u32::<LE>()?;
-        let folder_hash = self.inner.read_u32::<LE>()?;
-        let offset = self.inner.read_u32::<LE>()?;
-        let dat_file = ((offset & 0x7) >> 1) as u8;
-        let data_offset = ((offset & 0xfffffff8) << 3) as u32;
-        self.inner.read_u32::<LE>()?;
+        let file_hash = self.endian.read_u32(&mut self.inner)?;
+        let folder_hash = self.endian.read_u32(&mut self.inner)?;
+        let (dat_file, data_offset) = split_data_offset(self.endian.read_u32(&mut self.inner)?);
+        self.endian.read_u32(&mut self.inner)?;
         Ok(IndexFileEntry {
             path_hash: SqIndexHash {
                 file_hash,
@@ -240,9 +250,9 @@ impl<R: Read + Seek> IndexReader<R> {
     /// or you may get corrupted data. See [`seek_folders`](method.seek_folders.html). After
     /// execution, the underlying cursor is at the next file, if it exists.
     pub fn read_folder_entry(&mut self) -> SqResult<IndexFolderInfo> {
-        let folder_hash = self.inner.read_u32::<LE>()?;
-        let files_offset = self.inner.read_u32::<LE>()?;
-        let files_size = self.inner.read_u32::<LE>()?;
+        let folder_hash = self.endian.read_u32(&mut self.inner)?;
+        let files_offset = self.endian.read_u32(&mut self.inner)?;
+        let files_size = self.endian.read_u32(&mut self.inner)?;
         self.inner.seek(SeekFrom::Current(4))?;
         let files_count = files_size >> 4;
         Ok(IndexFolderInfo {
@@ -254,7 +264,7 @@ impl<R: Read + Seek> IndexReader<R> {
 
     /// Creates an iterator over the folder entries of the reader.
     /// *Note*: you cannot use this method to obtain file info. See [`folder_contents`](method.folder_contents.html).
-    pub fn folders(&mut self) -> SqResult<IndexFolders<R>> {
+    pub fn folders(&mut self) -> SqResult<IndexFolders<'_, R>> {
         let count = self.folders_count()?;
         self.seek_folders()?;
         Ok(IndexFolders {
@@ -270,11 +280,75 @@ impl<R: Read + Seek> IndexReader<R> {
         Ok(())
     }
 
+    /// Reads the descriptor of the segment `kind` from the index header.
+    pub fn segment(&mut self, kind: SegmentKind) -> SqResult<SegmentInfo> {
+        let header_len = self.header.header_size;
+        SegmentInfo::read(&mut self.inner, header_len, kind, self.endian)
+    }
+
+    /// Reads the descriptors of every segment of the index, in the order they are described by
+    /// the index header.
+    pub fn segments(&mut self) -> SqResult<Vec<SegmentInfo>> {
+        SegmentKind::ALL
+            .iter()
+            .map(|&kind| self.segment(kind))
+            .collect()
+    }
+
+    /// Reads the number of .dat files the entries of this index point into.
+    pub fn dat_count(&mut self) -> SqResult<u32> {
+        let header_len = self.header.header_size;
+        self.inner
+            .seek(SeekFrom::Start(header_len as u64 + DAT_COUNT_OFFSET))?;
+        Ok(self.endian.read_u32(&mut self.inner)?)
+    }
+
+    /// Recomputes the SHA-1 digests of the headers and segments of the index, reporting any that
+    /// do not match the digests stored in the file.
+    pub fn verify(&mut self) -> SqResult<VerifyReport> {
+        let header_len = self.header.header_size;
+        verify_index(&mut self.inner, header_len, self.endian)
+    }
+
+    /// Creates an iterator over the entries of the synonym segment.
+    pub fn synonyms(&mut self) -> SqResult<IndexSynonyms<'_, R>> {
+        let segment = self.segment(SegmentKind::Synonyms)?;
+        self.inner.seek(SeekFrom::Start(segment.offset as u64))?;
+        Ok(IndexSynonyms {
+            reader: self,
+            count: (segment.size / SYNONYM_ENTRY_SIZE) as usize,
+            visited: 0,
+        })
+    }
+
+    /// Reads a synonym entry from the index file. The underlying reader must be at a synonym
+    /// entry, or you may get corrupted data. After execution, the underlying cursor is at the
+    /// next entry, if it exists.
+    pub fn read_synonym_entry(&mut self) -> SqResult<IndexSynonymEntry> {
+        let file_hash = self.endian.read_u32(&mut self.inner)?;
+        let folder_hash = self.endian.read_u32(&mut self.inner)?;
+        let (dat_file, data_offset) = split_data_offset(self.endian.read_u32(&mut self.inner)?);
+        let synonym_index = self.endian.read_u32(&mut self.inner)?;
+        let mut path = [0; SYNONYM_ENTRY_SIZE as usize - 0x10];
+        self.inner.read_exact(&mut path)?;
+        let len = path.iter().position(|&b| b == 0).unwrap_or(path.len());
+        Ok(IndexSynonymEntry {
+            path_hash: SqIndexHash {
+                file_hash,
+                folder_hash,
+            },
+            data_offset,
+            dat_file,
+            synonym_index,
+            path: String::from_utf8_lossy(&path[..len]).into_owned(),
+        })
+    }
+
     /// Creates an iterator over the contents of the folder identified by `folder_info`
     pub fn folder_contents(
         &mut self,
         folder_info: &IndexFolderInfo,
-    ) -> SqResult<IndexFolderContents<R>> {
+    ) -> SqResult<IndexFolderContents<'_, R>> {
         self.seek_folder_contents(folder_info)?;
         Ok(IndexFolderContents {
             reader: self,
@@ -284,6 +358,48 @@ impl<R: Read + Seek> IndexReader<R> {
     }
 }
 
+/// Reads the header of a SqPack index file (either `.index` or `.index2`), checking that `inner`
+/// is a reader over one. The reader position is not guaranteed to be the same after calling.
+pub(super) fn read_index_header<R: Read + Seek>(inner: &mut R) -> SqResult<SqPackHeader> {
+    match SqPackHeader::read(inner) {
+        Ok(header) if header.file_type == SqPackFileType::Index => Ok(header),
+        Ok(_) | Err(SqpackError::NotSqPack) => Err(SqpackError::IndexReaderIsNotIndex),
+        Err(err) => Err(err),
+    }
+}
+
+/// Verifies the digests of an index file (either `.index` or `.index2`) with a SqPack header of
+/// `header_len`. Empty segments are skipped.
+pub(super) fn verify_index<R: Read + Seek>(
+    inner: &mut R,
+    header_len: u32,
+    endian: Endian,
+) -> SqResult<VerifyReport> {
+    let mut report = VerifyReport::default();
+    report.check_header(inner, HashedRegion::SqPackHeader, 0)?;
+    report.check_header(inner, HashedRegion::IndexHeader, header_len as u64)?;
+    for kind in SegmentKind::ALL {
+        let segment = SegmentInfo::read(inner, header_len, kind, endian)?;
+        if segment.size != 0 {
+            report.check(
+                inner,
+                HashedRegion::Segment(kind),
+                segment.offset as u64,
+                segment.size as u64,
+                segment.sha1,
+            )?;
+        }
+    }
+    Ok(report)
+}
+
+/// Splits the packed data location of an index entry into the .dat file number and the offset of
+/// the data within that .dat file. The offset is stored in units of 0x80 bytes, so it may point
+/// past 4 GiB.
+pub(super) fn split_data_offset(offset: u32) -> (u8, u64) {
+    (((offset & 0xe) >> 1) as u8, ((offset & !0xf) as u64) << 3)
+}
+
 impl<'a, R: Read + Seek> Iterator for IndexFolderContents<'a, R> {
     type Item = SqResult<IndexFileEntry>;
     fn next(&mut self) -> Option<Self::Item> {
@@ -312,6 +428,22 @@ impl<'a, R: Read + Seek> Iterator for IndexFiles<'a, R> {
     }
 }
 
+impl<'a, R: Read + Seek> Iterator for IndexSynonyms<'a, R> {
+    type Item = SqResult<IndexSynonymEntry>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.visited < self.count {
+            self.visited += 1;
+            Some(self.reader.read_synonym_entry())
+        } else {
+            None
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let c = self.count - self.visited;
+        (c, Some(c))
+    }
+}
+
 impl<'a, R: Read + Seek> Iterator for IndexFolders<'a, R> {
     type Item = SqResult<IndexFolderInfo>;
     fn next(&mut self) -> Option<Self::Item> {
@@ -327,3 +459,66 @@ impl<'a, R: Read + Seek> Iterator for IndexFolders<'a, R> {
         (c, Some(c))
     }
 }
+
+#[cfg(test)]
+mod reader_tests {
+    use crate::{
+        io::{
+            index::{reader::split_data_offset, IndexReader, SegmentKind},
+            writer::SqPackWriter,
+        },
+        SqPath,
+    };
+    use std::io::Cursor;
+
+    #[test]
+    fn data_offset_past_4gib() {
+        assert_eq!(split_data_offset(0x0000_0012), (1, 0x80));
+        assert_eq!(split_data_offset(0x2000_0004), (2, 0x1_0000_0000));
+        assert_eq!(split_data_offset(0xffff_fffe), (7, 0x7_ffff_ff80));
+    }
+
+    #[test]
+    fn segments_and_synonyms() {
+        let mut writer = SqPackWriter::new();
+        writer.add("exd/root.exl", b"root").unwrap();
+        let mut index = Vec::new();
+        writer.write_index(&mut index).unwrap();
+
+        // append a synonym entry and point the synonym segment at it
+        let synonym_offset = index.len() as u32;
+        let hash = SqPath::new("exd/root.exl").sq_index_hash().unwrap();
+        index.extend_from_slice(&hash.file_hash.to_le_bytes());
+        index.extend_from_slice(&hash.folder_hash.to_le_bytes());
+        index.extend_from_slice(&0x12u32.to_le_bytes());
+        index.extend_from_slice(&1u32.to_le_bytes());
+        let mut path = [0u8; 0xF0];
+        path[..12].copy_from_slice(b"exd/root.exl");
+        index.extend_from_slice(&path);
+        index[0x454..0x458].copy_from_slice(&synonym_offset.to_le_bytes());
+        index[0x458..0x45C].copy_from_slice(&0x100u32.to_le_bytes());
+
+        let mut reader = IndexReader::new(Cursor::new(index)).unwrap();
+        assert_eq!(reader.dat_count().unwrap(), 1);
+        let segments = reader.segments().unwrap();
+        assert_eq!(segments.len(), 4);
+        assert_eq!(segments[0].kind, SegmentKind::Files);
+        assert_eq!(segments[0].offset, 0x800);
+        assert_eq!(segments[0].size, 0x10);
+        assert_eq!(segments[3].kind, SegmentKind::Folders);
+        assert_eq!(segments[3].offset, 0x810);
+        assert_eq!(segments[3].size, 0x10);
+
+        let synonyms = reader
+            .synonyms()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(synonyms.len(), 1);
+        assert_eq!(synonyms[0].path_hash, hash);
+        assert_eq!(synonyms[0].dat_file, 1);
+        assert_eq!(synonyms[0].data_offset, 0x80);
+        assert_eq!(synonyms[0].synonym_index, 1);
+        assert_eq!(synonyms[0].path, "exd/root.exl");
+    }
+}
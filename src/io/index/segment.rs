@@ -0,0 +1,81 @@
+use crate::{error::SqResult, io::endian::Endian};
+use std::io::{Read, Seek, SeekFrom};
+
+/// The offset relative to the sqpack header end to find the number of .dat files of the index
+pub(super) const DAT_COUNT_OFFSET: u64 = 0x50;
+
+/// The size of an entry in the synonym segment
+pub(super) const SYNONYM_ENTRY_SIZE: u32 = 0x100;
+
+/// The segments of an index file, in the order they are described by the index header
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum SegmentKind {
+    /// The file entries, sorted by hash
+    Files,
+    /// The entries of files whose hashes collide, which also store the full path
+    Synonyms,
+    /// Describes unused space within the .dat files
+    EmptyBlocks,
+    /// The folder entries, each pointing at a range of the files segment
+    Folders,
+}
+
+impl SegmentKind {
+    /// All segments, in the order they are described by the index header
+    pub const ALL: [SegmentKind; 4] = [
+        SegmentKind::Files,
+        SegmentKind::Synonyms,
+        SegmentKind::EmptyBlocks,
+        SegmentKind::Folders,
+    ];
+
+    /// The offset relative to the sqpack header end to find the descriptor of this segment
+    pub(super) fn info_offset(&self) -> u64 {
+        match self {
+            SegmentKind::Files => 0x08,
+            SegmentKind::Synonyms => 0x54,
+            SegmentKind::EmptyBlocks => 0x9C,
+            SegmentKind::Folders => 0xE4,
+        }
+    }
+}
+
+/// Describes where a segment is located within an index file, and the hash of its contents
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct SegmentInfo {
+    /// Which segment this is
+    pub kind: SegmentKind,
+
+    /// The offset of the segment from the start of the index file
+    pub offset: u32,
+
+    /// The size of the segment in bytes
+    pub size: u32,
+
+    /// The SHA-1 hash of the contents of the segment
+    pub sha1: [u8; 20],
+}
+
+impl SegmentInfo {
+    /// Reads the descriptor of the segment `kind` from an index file with a SqPack header of
+    /// `header_length` and byte order `endian`. The reader position is not guaranteed to be the
+    /// same after calling.
+    pub(super) fn read<R: Read + Seek>(
+        reader: &mut R,
+        header_length: u32,
+        kind: SegmentKind,
+        endian: Endian,
+    ) -> SqResult<SegmentInfo> {
+        reader.seek(SeekFrom::Start(header_length as u64 + kind.info_offset()))?;
+        let offset = endian.read_u32(reader)?;
+        let size = endian.read_u32(reader)?;
+        let mut sha1 = [0; 20];
+        reader.read_exact(&mut sha1)?;
+        Ok(SegmentInfo {
+            kind,
+            offset,
+            size,
+            sha1,
+        })
+    }
+}
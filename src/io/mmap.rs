@@ -0,0 +1,118 @@
+use crate::error::SqResult;
+use memmap2::Mmap;
+use std::{
+    fs::File,
+    io::{Error as IOError, ErrorKind},
+    path::Path,
+    sync::Arc,
+};
+
+/// An index or .dat file mapped into memory. Cloning the handle shares the mapping, so a file
+/// only needs to be mapped once no matter how many files are read from it.
+///
+/// The game must not modify the files while they are mapped, such as by patching them.
+///
+/// # Examples
+/// ```no_run
+/// use sqpack::io::{dat::SqFile, index::IndexCache, mmap::MappedFile};
+/// use std::io::Read;
+///
+/// let index = MappedFile::open("/home/uwu/ffxiv/game/sqpack/ffxiv/0c0000.win32.index").unwrap();
+/// let dat = MappedFile::open("/home/uwu/ffxiv/game/sqpack/ffxiv/0c0000.win32.dat0").unwrap();
+/// let cache = IndexCache::from_mapped(&index).unwrap();
+/// let entry = cache.get("music/ffxiv/BGM_System_Title.scd").unwrap();
+/// let mut file = SqFile::open_mapped(&dat, *entry).unwrap();
+/// let mut data = Vec::with_capacity(file.total_size());
+/// file.read_to_end(&mut data).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct MappedFile {
+    map: Arc<Mmap>,
+}
+
+impl MappedFile {
+    /// Maps the file at `path` into memory.
+    pub fn open<P: AsRef<Path>>(path: P) -> SqResult<MappedFile> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is read-only, and the game files are not expected to change while
+        // they are being read, as documented on the type.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(MappedFile { map: Arc::new(map) })
+    }
+
+    /// Returns the contents of the file.
+    pub fn as_slice(&self) -> &[u8] { &self.map }
+
+    /// Creates a handle to `len` bytes of the mapping starting at `start`.
+    pub(crate) fn slice(&self, start: u64, len: usize) -> Result<MappedSlice, IOError> {
+        let start = usize::try_from(start).map_err(|_| IOError::from(ErrorKind::UnexpectedEof))?;
+        match start.checked_add(len) {
+            Some(end) if end <= self.map.len() => Ok(MappedSlice {
+                file: self.clone(),
+                start,
+                end,
+            }),
+            _ => Err(IOError::from(ErrorKind::UnexpectedEof)),
+        }
+    }
+}
+
+impl AsRef<[u8]> for MappedFile {
+    fn as_ref(&self) -> &[u8] { self.as_slice() }
+}
+
+/// A range of a mapped file, which keeps the mapping alive while it is read
+pub(crate) struct MappedSlice {
+    file: MappedFile,
+    start: usize,
+    end: usize,
+}
+
+impl AsRef<[u8]> for MappedSlice {
+    fn as_ref(&self) -> &[u8] { &self.file.as_slice()[self.start..self.end] }
+}
+
+#[cfg(test)]
+mod mmap_tests {
+    use crate::{
+        io::{
+            dat::SqFile,
+            index::{IndexCache, IndexReader},
+            mmap::MappedFile,
+        },
+        test_util::TempSqPack,
+        SqPack,
+    };
+    use std::{fs, io::Read};
+
+    #[test]
+    fn reads_mapped_files() {
+        let large = (0..50_000u32).map(|i| (i % 13) as u8).collect::<Vec<_>>();
+        let temp = TempSqPack::new("mmap");
+        let index_path = temp.write(
+            "ffxiv/0c0000.win32.index",
+            &[
+                ("music/ffxiv/bgm_large.scd", &large[..]),
+                ("music/ffxiv/bgm_small.scd", b"small"),
+            ],
+        );
+
+        let index = MappedFile::open(&index_path).unwrap();
+        let cache = IndexCache::from_mapped(&index).unwrap();
+        let mut reader = IndexReader::new(fs::File::open(&index_path).unwrap()).unwrap();
+        assert_eq!(cache, IndexCache::from_reader(&mut reader).unwrap());
+
+        let dat = MappedFile::open(index_path.with_extension("dat0")).unwrap();
+        let entry = cache.get("music/ffxiv/bgm_large.scd").unwrap();
+        let mut file = SqFile::open_mapped(&dat, *entry).unwrap();
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).unwrap();
+        assert_eq!(data, large);
+
+        let sqpack = SqPack::new(temp.root());
+        let mut data = Vec::new();
+        let mut file = sqpack.open_mapped("music/ffxiv/bgm_small.scd").unwrap();
+        file.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"small");
+    }
+}
@@ -1,5 +1,28 @@
+mod endian;
+
+/// Types relating to the header shared by index and .dat files
+pub mod header;
+
 /// Types and functions relating to index files.
 pub mod index;
 
 /// Types and functions relating to .dat files
 pub mod dat;
+
+/// Types relating to reading index and .dat files mapped into memory
+#[cfg(feature = "mmap")]
+pub mod mmap;
+
+/// Types for reading index and .dat files asynchronously with tokio
+#[cfg(feature = "async")]
+pub mod async_io;
+
+/// Functions for extracting every file of index files in parallel
+#[cfg(feature = "rayon")]
+pub mod extract;
+
+/// Types relating to verifying the SHA-1 digests stored in index and .dat files
+pub mod verify;
+
+/// Types and functions relating to creating new index and .dat files
+pub mod writer;
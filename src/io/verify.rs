@@ -0,0 +1,145 @@
+use crate::{
+    error::SqResult,
+    io::{header::SQPACK_HEADER_SHA1_OFFSET, index::SegmentKind},
+};
+use sha1::{Digest, Sha1};
+use std::io::{copy, Read, Seek, SeekFrom};
+
+/// A region of an index or .dat file that carries a SHA-1 digest
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum HashedRegion {
+    /// The SqPack header at the start of every index and .dat file
+    SqPackHeader,
+    /// The index header that follows the SqPack header of an index file
+    IndexHeader,
+    /// The contents of a segment of an index file
+    Segment(SegmentKind),
+    /// The .dat header that follows the SqPack header of a .dat file
+    DatHeader,
+    /// The file data following the headers of a .dat file
+    DatData,
+}
+
+/// A region whose contents did not match the digest stored for it
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct HashMismatch {
+    /// Which region of the file failed verification
+    pub region: HashedRegion,
+
+    /// The offset of the region from the start of the file
+    pub offset: u64,
+
+    /// The size of the region in bytes, as described by the file
+    pub size: u64,
+
+    /// The digest stored in the file
+    pub expected: [u8; 20],
+
+    /// The digest of the region as it was read
+    pub actual: [u8; 20],
+}
+
+/// The result of verifying the digests of an index or .dat file. Regions that are cut off by the
+/// end of the file are hashed up to the end, and so are reported as mismatches.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct VerifyReport {
+    /// The regions that were checked, in the order they appear in the headers
+    pub checked: Vec<HashedRegion>,
+
+    /// The regions that did not match their stored digest
+    pub mismatches: Vec<HashMismatch>,
+}
+
+impl VerifyReport {
+    /// Returns true if every checked region matched its stored digest.
+    pub fn is_ok(&self) -> bool { self.mismatches.is_empty() }
+
+    /// Hashes `size` bytes of `reader` at `offset` and compares them against `expected`, recording
+    /// the result.
+    pub(crate) fn check<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        region: HashedRegion,
+        offset: u64,
+        size: u64,
+        expected: [u8; 20],
+    ) -> SqResult<()> {
+        let actual = sha1_of_region(reader, offset, size)?;
+        self.checked.push(region);
+        if actual != expected {
+            self.mismatches.push(HashMismatch {
+                region,
+                offset,
+                size,
+                expected,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks the self hash of the header at `offset`. Both the SqPack header and the header that
+    /// follows it store the hash of their preceding bytes at the same offset.
+    pub(crate) fn check_header<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        region: HashedRegion,
+        offset: u64,
+    ) -> SqResult<()> {
+        let expected = read_sha1(reader, offset + SQPACK_HEADER_SHA1_OFFSET)?;
+        self.check(reader, region, offset, SQPACK_HEADER_SHA1_OFFSET, expected)
+    }
+}
+
+/// Computes the SHA-1 digest of `data`.
+pub(crate) fn sha1(data: &[u8]) -> [u8; 20] { Sha1::digest(data).into() }
+
+/// Reads a stored SHA-1 digest at `offset`.
+pub(crate) fn read_sha1<R: Read + Seek>(reader: &mut R, offset: u64) -> SqResult<[u8; 20]> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut digest = [0; 20];
+    reader.read_exact(&mut digest)?;
+    Ok(digest)
+}
+
+/// Computes the SHA-1 digest of `size` bytes at `offset`, or up to the end of the reader if it is
+/// shorter.
+fn sha1_of_region<R: Read + Seek>(reader: &mut R, offset: u64, size: u64) -> SqResult<[u8; 20]> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut hasher = Sha1::new();
+    copy(&mut reader.take(size), &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod verify_tests {
+    use crate::io::verify::{sha1, HashedRegion, VerifyReport};
+    use std::io::Cursor;
+
+    #[test]
+    fn known_digest() {
+        assert_eq!(
+            sha1(b"abc"),
+            [
+                0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e, 0x25, 0x71, 0x78, 0x50,
+                0xc2, 0x6c, 0x9c, 0xd0, 0xd8, 0x9d
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_truncation() {
+        let mut data = Cursor::new(b"xxabcdef".to_vec());
+        let mut report = VerifyReport::default();
+        report
+            .check(&mut data, HashedRegion::DatData, 2, 3, sha1(b"abc"))
+            .unwrap();
+        assert!(report.is_ok());
+        report
+            .check(&mut data, HashedRegion::DatData, 2, 10, sha1(b"abcdefgh"))
+            .unwrap();
+        assert_eq!(report.checked.len(), 2);
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(report.mismatches[0].actual, sha1(b"abcdef"));
+    }
+}
@@ -0,0 +1,500 @@
+use crate::{
+    error::{SqResult, SqpackError},
+    io::{
+        dat::{dat_reader::DAT_DATA_UNIT, sqfile::dat_path},
+        header::{SqPackFileType, SqPackHeader, SQPACK_HEADER_SHA1_OFFSET, SQPACK_HEADER_SIZE},
+        verify::sha1,
+    },
+    sqpath::{Expansion, FileType, SqPackNumber, SqPath},
+};
+use byteorder::{WriteBytesExt, LE};
+use flate2::{write::DeflateEncoder, Compression};
+use sha1::{Digest, Sha1};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Error as IOError, ErrorKind, Write},
+    path::Path,
+};
+
+/// The size of the header following the SqPack header in index and .dat files
+const SEGMENT_HEADER_SIZE: usize = 0x400;
+
+/// The offset of the first file's data within a .dat file
+const DAT_DATA_OFFSET: u64 = (SQPACK_HEADER_SIZE + SEGMENT_HEADER_SIZE) as u64;
+
+/// The content type of Binary files, the only type the writer produces
+const BINARY_CONTENT_TYPE: u32 = 2;
+
+/// The maximum amount of data stored in a single block
+const BLOCK_SIZE: usize = 16000;
+
+/// The alignment of data headers and blocks within a .dat file
+const ALIGNMENT: usize = 0x80;
+
+/// The size of the block headers and data headers up to the block table
+const BLOCK_HEADER_SIZE: usize = 0x10;
+const DATA_HEADER_SIZE: usize = 0x18;
+
+/// The default maximum size of a single .dat file, matching the game's own limit
+pub const DEFAULT_MAX_DAT_SIZE: u64 = 2_000_000_000;
+
+/// The most .dat files a single index can refer to
+const MAX_DAT_FILES: usize = 8;
+
+/// Builds a new `.index` file and its `.datN` files from loose files. Files are compressed when
+/// they are added, and the resulting SqPack can be read back with
+/// [`IndexReader`](../index/struct.IndexReader.html) and [`SqFile`](../dat/struct.SqFile.html).
+///
+/// All files added to one writer must resolve to the same index file, see
+/// [`SqPath::sqpack_index_path`](../../sqpath/struct.SqPath.html#method.sqpack_index_path).
+///
+/// # Examples
+/// ```no_run
+/// use sqpack::io::writer::SqPackWriter;
+///
+/// let mut writer = SqPackWriter::new();
+/// writer.add("music/ffxiv/BGM_Custom.scd", b"...").unwrap();
+/// writer.write_files("/tmp/sqpack/ffxiv/0c0000.win32.index").unwrap();
+/// ```
+pub struct SqPackWriter {
+    /// The compressed files, keyed and sorted by folder hash and then file hash
+    entries: BTreeMap<(u32, u32), Box<[u8]>>,
+    /// The size a .dat file may grow to before files are placed in the next one
+    max_dat_size: u64,
+    /// The category, expansion and chunk of the index file, set by the first file added
+    index_file: Option<(FileType, Expansion, Option<SqPackNumber>)>,
+}
+
+/// Where a file written by the writer is placed
+#[derive(Copy, Clone)]
+struct Placement {
+    dat_file: u8,
+    data_offset: u64,
+}
+
+impl SqPackWriter {
+    /// Creates an empty writer that splits data into .dat files of at most
+    /// [`DEFAULT_MAX_DAT_SIZE`](constant.DEFAULT_MAX_DAT_SIZE.html) bytes.
+    pub fn new() -> Self { Self::with_max_dat_size(DEFAULT_MAX_DAT_SIZE) }
+
+    /// Creates an empty writer that splits data into .dat files of at most `max_dat_size` bytes.
+    /// A single file larger than this is still written, alone in its own .dat file.
+    pub fn with_max_dat_size(max_dat_size: u64) -> Self {
+        SqPackWriter {
+            entries: BTreeMap::new(),
+            max_dat_size,
+            index_file: None,
+        }
+    }
+
+    /// Compresses `data` and adds it to the SqPack at `sqpath`, replacing any data previously
+    /// added at the same path.
+    ///
+    /// # Returns
+    /// `Err(SqpackError::InvalidSqPath(...))` if `sqpath` could not be parsed, or
+    /// `Err(SqpackError::MismatchedIndexFile(...))` if it belongs to a different index file than
+    /// the files added before it.
+    pub fn add<SQ: AsRef<SqPath>>(&mut self, sqpath: SQ, data: &[u8]) -> SqResult<()> {
+        let sqpath = sqpath.as_ref();
+        let parsed = sqpath.parse()?;
+        let index_file = (parsed.file_type, parsed.expansion, parsed.chunk);
+        if *self.index_file.get_or_insert(index_file) != index_file {
+            return Err(SqpackError::MismatchedIndexFile(
+                sqpath.as_str().to_string(),
+            ));
+        }
+
+        let hash = parsed.sq_index_hash();
+        self.entries
+            .insert((hash.folder_hash, hash.file_hash), pack_binary(data)?);
+        Ok(())
+    }
+
+    /// The number of files added to the writer.
+    pub fn len(&self) -> usize { self.entries.len() }
+
+    /// Checks whether no files were added to the writer.
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    /// The number of .dat files the added files will be written to.
+    pub fn dat_count(&self) -> SqResult<u8> {
+        Ok(self
+            .placements()?
+            .last()
+            .map(|placement| placement.dat_file + 1)
+            .unwrap_or(1))
+    }
+
+    /// Writes the index file to `writer`.
+    pub fn write_index<W: Write>(&self, writer: &mut W) -> SqResult<()> {
+        let placements = self.placements()?;
+        let dat_count = placements.last().map(|p| p.dat_file + 1).unwrap_or(1);
+
+        // the segments follow the headers: files, synonyms, empty blocks, then folders
+        let files_offset = SQPACK_HEADER_SIZE + SEGMENT_HEADER_SIZE;
+        let mut files = Vec::with_capacity(self.entries.len() * 0x10);
+        let mut folders: Vec<u8> = Vec::new();
+        let mut current_folder: Option<(u32, usize)> = None;
+        for (&(folder_hash, file_hash), placement) in self.entries.keys().zip(placements.iter()) {
+            if current_folder.map(|(hash, _)| hash) != Some(folder_hash) {
+                if let Some((hash, start)) = current_folder {
+                    write_folder_entry(&mut folders, hash, start, files.len(), files_offset)?;
+                }
+                current_folder = Some((folder_hash, files.len()));
+            }
+            files.write_u32::<LE>(file_hash)?;
+            files.write_u32::<LE>(folder_hash)?;
+            files.write_u32::<LE>(pack_data_offset(*placement)?)?;
+            files.write_u32::<LE>(0)?;
+        }
+        if let Some((hash, start)) = current_folder {
+            write_folder_entry(&mut folders, hash, start, files.len(), files_offset)?;
+        }
+        let folders_offset = files_offset + files.len();
+
+        let mut header = Vec::with_capacity(SEGMENT_HEADER_SIZE);
+        header.write_u32::<LE>(SEGMENT_HEADER_SIZE as u32)?;
+        header.write_u32::<LE>(1)?;
+        write_segment(&mut header, files_offset, &files)?;
+        header.write_u32::<LE>(dat_count as u32)?;
+        write_segment(&mut header, folders_offset, &[])?;
+        write_segment(&mut header, folders_offset, &[])?;
+        write_segment(&mut header, folders_offset, &folders)?;
+        let header = finish_header(header);
+
+        writer.write_all(&sqpack_header(SqPackFileType::Index)?)?;
+        writer.write_all(&header)?;
+        writer.write_all(&files)?;
+        writer.write_all(&folders)?;
+        Ok(())
+    }
+
+    /// Writes the .dat file numbered `dat_file` to `writer`.
+    pub fn write_dat<W: Write>(&self, dat_file: u8, writer: &mut W) -> SqResult<()> {
+        let placements = self.placements()?;
+        let data = self
+            .entries
+            .values()
+            .zip(placements.iter())
+            .filter(|(_, placement)| placement.dat_file == dat_file)
+            .map(|(data, _)| data);
+        let data_size: usize = data.clone().map(|data| data.len()).sum();
+        let mut hasher = Sha1::new();
+        data.clone().for_each(|data| hasher.update(data));
+
+        let mut header = Vec::with_capacity(SEGMENT_HEADER_SIZE);
+        header.write_u32::<LE>(SEGMENT_HEADER_SIZE as u32)?;
+        header.write_u32::<LE>(0)?;
+        header.write_u32::<LE>(0x10)?;
+        header.write_u32::<LE>((data_size as u64 / DAT_DATA_UNIT) as u32)?;
+        header.write_u32::<LE>(dat_file as u32 + 1)?;
+        header.write_u32::<LE>(0)?;
+        header.write_u64::<LE>(self.max_dat_size)?;
+        header.write_all(&<[u8; 20]>::from(hasher.finalize()))?;
+        let header = finish_header(header);
+
+        writer.write_all(&sqpack_header(SqPackFileType::Dat)?)?;
+        writer.write_all(&header)?;
+        for data in data {
+            writer.write_all(data)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the index file to `index_path`, and the .dat files next to it.
+    pub fn write_files<P: AsRef<Path>>(&self, index_path: P) -> SqResult<()> {
+        let index_path = index_path.as_ref();
+        let mut index = BufWriter::new(File::create(index_path)?);
+        self.write_index(&mut index)?;
+        index.flush()?;
+        for dat_file in 0..self.dat_count()? {
+            let mut dat = BufWriter::new(File::create(dat_path(index_path, dat_file)?)?);
+            self.write_dat(dat_file, &mut dat)?;
+            dat.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Assigns every file, in order, to a .dat file and an offset within it.
+    fn placements(&self) -> SqResult<Vec<Placement>> {
+        let mut placements = Vec::with_capacity(self.entries.len());
+        let mut current = Placement {
+            dat_file: 0,
+            data_offset: DAT_DATA_OFFSET,
+        };
+        for data in self.entries.values() {
+            let size = data.len() as u64;
+            if current.data_offset != DAT_DATA_OFFSET
+                && current.data_offset + size > self.max_dat_size
+            {
+                current = Placement {
+                    dat_file: current.dat_file + 1,
+                    data_offset: DAT_DATA_OFFSET,
+                };
+            }
+            if current.dat_file as usize >= MAX_DAT_FILES {
+                return Err(
+                    IOError::new(ErrorKind::InvalidInput, "too much data for one index").into(),
+                );
+            }
+            placements.push(current);
+            current.data_offset += size;
+        }
+        Ok(placements)
+    }
+}
+
+impl Default for SqPackWriter {
+    fn default() -> Self { Self::new() }
+}
+
+/// Creates the SqPack header found at the start of index and .dat files
+fn sqpack_header(file_type: SqPackFileType) -> SqResult<Box<[u8]>> {
+    let header = SqPackHeader::new(file_type).to_bytes()?;
+    Ok(finish_header(header.into_vec()).into_boxed_slice())
+}
+
+/// Pads a header to its full size and stores the hash of its contents at the end
+fn finish_header(header: Vec<u8>) -> Vec<u8> {
+    let mut header = header;
+    let hash_offset = SQPACK_HEADER_SHA1_OFFSET as usize;
+    header.resize(SEGMENT_HEADER_SIZE, 0);
+    let hash = sha1(&header[..hash_offset]);
+    header[hash_offset..hash_offset + hash.len()].copy_from_slice(&hash);
+    header
+}
+
+/// Writes the offset, size and hash of a segment. Empty segments are left without a hash.
+fn write_segment(header: &mut Vec<u8>, offset: usize, data: &[u8]) -> SqResult<()> {
+    header.write_u32::<LE>(offset as u32)?;
+    header.write_u32::<LE>(data.len() as u32)?;
+    let mut hash = [0; 0x40];
+    if !data.is_empty() {
+        hash[..20].copy_from_slice(&sha1(data));
+    }
+    header.write_all(&hash)?;
+    Ok(())
+}
+
+/// Writes a folder entry covering the files segment entries from `start` to `end`
+fn write_folder_entry(
+    folders: &mut Vec<u8>,
+    folder_hash: u32,
+    start: usize,
+    end: usize,
+    files_offset: usize,
+) -> SqResult<()> {
+    folders.write_u32::<LE>(folder_hash)?;
+    folders.write_u32::<LE>((files_offset + start) as u32)?;
+    folders.write_u32::<LE>((end - start) as u32)?;
+    folders.write_u32::<LE>(0)?;
+    Ok(())
+}
+
+/// Packs a placement into the data location stored in an index entry
+fn pack_data_offset(placement: Placement) -> SqResult<u32> {
+    let offset = u32::try_from(placement.data_offset >> 3)
+        .map_err(|_| IOError::new(ErrorKind::InvalidInput, "data offset too large"))?;
+    Ok(offset | ((placement.dat_file as u32) << 1))
+}
+
+/// Pads `buf` with zeroes to the next multiple of `ALIGNMENT`
+fn align(buf: &mut Vec<u8>) { buf.resize(buf.len().next_multiple_of(ALIGNMENT), 0); }
+
+/// Compresses `data` into the data header and blocks of a Binary file
+fn pack_binary(data: &[u8]) -> SqResult<Box<[u8]>> {
+    let mut table = Vec::new();
+    let mut blocks = Vec::new();
+    for chunk in data.chunks(BLOCK_SIZE) {
+        let offset = blocks.len();
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(chunk)?;
+        let compressed = encoder.finish()?;
+
+        // blocks that don't shrink are stored as-is
+        blocks.write_u32::<LE>(BLOCK_HEADER_SIZE as u32)?;
+        blocks.write_u32::<LE>(0)?;
+        if compressed.len() < chunk.len() {
+            blocks.write_u32::<LE>(compressed.len() as u32)?;
+            blocks.write_u32::<LE>(chunk.len() as u32)?;
+            blocks.write_all(&compressed)?;
+        } else {
+            blocks.write_u32::<LE>(32000)?;
+            blocks.write_u32::<LE>(chunk.len() as u32)?;
+            blocks.write_all(chunk)?;
+        }
+        align(&mut blocks);
+
+        table.write_u32::<LE>(offset as u32)?;
+        table.write_u16::<LE>((blocks.len() - offset) as u16)?;
+        table.write_u16::<LE>(chunk.len() as u16)?;
+    }
+
+    let block_count = table.len() / 8;
+    let mut packed = Vec::with_capacity(DATA_HEADER_SIZE + table.len() + blocks.len());
+    let header_len = (DATA_HEADER_SIZE + table.len()).next_multiple_of(ALIGNMENT);
+    packed.write_u32::<LE>(header_len as u32)?;
+    packed.write_u32::<LE>(BINARY_CONTENT_TYPE)?;
+    packed.write_u32::<LE>(data.len() as u32)?;
+    packed.write_u32::<LE>(0)?;
+    packed.write_u32::<LE>(0)?;
+    packed.write_u32::<LE>(block_count as u32)?;
+    packed.write_all(&table)?;
+    align(&mut packed);
+    packed.write_all(&blocks)?;
+    Ok(packed.into_boxed_slice())
+}
+
+#[cfg(test)]
+mod writer_tests {
+    use crate::{
+        error::SqpackError,
+        io::{
+            dat::{DatReader, SqFile},
+            index::{IndexCache, IndexReader, SegmentKind},
+            verify::HashedRegion,
+            writer::SqPackWriter,
+        },
+        SqPath,
+    };
+    use std::io::{Cursor, Read};
+
+    /// Writes the index and all dats of `writer` into memory
+    fn write_all(writer: &SqPackWriter) -> (Vec<u8>, Vec<Vec<u8>>) {
+        let mut index = Vec::new();
+        writer.write_index(&mut index).unwrap();
+        let dats = (0..writer.dat_count().unwrap())
+            .map(|dat_file| {
+                let mut dat = Vec::new();
+                writer.write_dat(dat_file, &mut dat).unwrap();
+                dat
+            })
+            .collect();
+        (index, dats)
+    }
+
+    /// Reads the file at `sqpath` back out of a written index and dats
+    fn read_back(index: &[u8], dats: &[Vec<u8>], sqpath: &str) -> Vec<u8> {
+        let mut reader = IndexReader::new(Cursor::new(index)).unwrap();
+        let cache = IndexCache::from_reader(&mut reader).unwrap();
+        let entry = *cache.get(sqpath).expect("File missing from index");
+        let dat = Cursor::new(&dats[entry.dat_file as usize]);
+        let mut file = SqFile::open_reader(dat, entry).unwrap();
+        let mut data = Vec::with_capacity(file.total_size());
+        file.read_to_end(&mut data).unwrap();
+        assert_eq!(data.len(), file.total_size());
+        data
+    }
+
+    #[test]
+    fn round_trip() {
+        let large = (0..100_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let files: [(&str, &[u8]); 4] = [
+            ("music/ffxiv/BGM_System_Title.scd", b"title"),
+            ("music/ffxiv/bgm_large.scd", &large),
+            ("music/ffxiv/bgm_empty.scd", b""),
+            ("music/ffxiv/event/bgm_other.scd", b"other music"),
+        ];
+
+        let mut writer = SqPackWriter::new();
+        for (path, data) in files.iter() {
+            writer.add(path, data).unwrap();
+        }
+        assert_eq!(writer.len(), files.len());
+        assert!(writer.add("no_folder", b"").is_err());
+        assert!(matches!(
+            writer.add("music/ex1/bgm_other.scd", b""),
+            Err(SqpackError::MismatchedIndexFile(_))
+        ));
+
+        let (index, dats) = write_all(&writer);
+        assert_eq!(dats.len(), 1);
+
+        let mut reader = IndexReader::new(Cursor::new(&index)).unwrap();
+        assert_eq!(reader.files_count().unwrap(), files.len());
+        assert_eq!(reader.folders_count().unwrap(), 2);
+        let cache = IndexCache::from_reader(&mut reader).unwrap();
+        assert_eq!(cache.len(), files.len());
+        for (path, data) in files.iter() {
+            assert!(cache.contains(SqPath::new(path)));
+            assert_eq!(&read_back(&index, &dats, path), data);
+        }
+    }
+
+    #[test]
+    fn splits_dats() {
+        let data = (0..40_000u32)
+            .map(|i| (i * 7 % 256) as u8)
+            .collect::<Vec<_>>();
+        let mut writer = SqPackWriter::with_max_dat_size(0x800 + 0x100);
+        let paths = ["exd/a.exd", "exd/b.exd", "exd/c.exd"];
+        for path in paths {
+            writer.add(path, &data).unwrap();
+        }
+        assert_eq!(writer.dat_count().unwrap(), 3);
+
+        let (index, dats) = write_all(&writer);
+        for path in paths {
+            assert_eq!(read_back(&index, &dats, path), data);
+        }
+    }
+
+    #[test]
+    fn verifies_hashes() {
+        let mut writer = SqPackWriter::new();
+        writer.add("exd/root.exl", b"EXLT,2").unwrap();
+        writer.add("exd/item.exh", b"EXHF").unwrap();
+        let (mut index, mut dats) = write_all(&writer);
+
+        let report = IndexReader::new(Cursor::new(&index))
+            .unwrap()
+            .verify()
+            .unwrap();
+        assert!(report.is_ok());
+        assert_eq!(
+            report.checked,
+            [
+                HashedRegion::SqPackHeader,
+                HashedRegion::IndexHeader,
+                HashedRegion::Segment(SegmentKind::Files),
+                HashedRegion::Segment(SegmentKind::Folders),
+            ]
+        );
+        let report = DatReader::new(Cursor::new(&dats[0]))
+            .unwrap()
+            .verify()
+            .unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.checked.len(), 3);
+
+        // tamper with a file entry and the last byte of file data
+        index[0x808] ^= 1;
+        *dats[0].last_mut().unwrap() ^= 1;
+        let report = IndexReader::new(Cursor::new(&index))
+            .unwrap()
+            .verify()
+            .unwrap();
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(
+            report.mismatches[0].region,
+            HashedRegion::Segment(SegmentKind::Files)
+        );
+        assert_eq!(report.mismatches[0].offset, 0x800);
+        let report = DatReader::new(Cursor::new(&dats[0]))
+            .unwrap()
+            .verify()
+            .unwrap();
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(report.mismatches[0].region, HashedRegion::DatData);
+
+        // truncating the data is reported rather than failing
+        let len = dats[0].len();
+        dats[0].truncate(len - 0x80);
+        let report = DatReader::new(Cursor::new(&dats[0]))
+            .unwrap()
+            .verify()
+            .unwrap();
+        assert_eq!(report.mismatches.len(), 1);
+    }
+}
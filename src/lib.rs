@@ -3,7 +3,7 @@
 
 //! A simple crate for reading the data files of FFXIV.
 //!
-//! The main entry point for most use cases is the `SqPack` struct,
+//! The main entry point for most use cases is the [`SqPack`] struct,
 //! which accepts a path to the data files on the user's machine.
 //! From an instance of this struct, you can open `Read` implementors
 //! which return the decoded data.
@@ -29,14 +29,36 @@ mod hash_consts;
 /// Module for errors specific to SqPack reading and processing
 pub mod error;
 
-pub use sqpath::SqPath;
+mod sqpack;
+
+/// Fixtures shared by the tests of several modules
+#[cfg(test)]
+mod test_util;
+
+pub use crate::{sqpack::SqPack, sqpath::SqPath};
+
+use crate::error::SqResult;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
 
 /// Utility function to create a buffer with the specified size
-pub(crate) fn buffer(size: usize) -> Box<[u8]> {
-    let mut buf = Vec::with_capacity(size);
-    let buf = unsafe {
-        buf.set_len(size);
-        buf.into_boxed_slice()
-    };
-    buf
+pub(crate) fn buffer(size: usize) -> Box<[u8]> { vec![0; size].into_boxed_slice() }
+
+/// Utility function to retrieve the value cached for `path` in `cache`, creating it with `open` if
+/// it was not yet cached. `open` runs without holding the lock; if two threads open the same path
+/// at once, the first value cached is kept.
+pub(crate) fn open_cached<T: Clone>(
+    cache: &Mutex<HashMap<PathBuf, T>>,
+    path: &Path,
+    open: impl FnOnce(&Path) -> SqResult<T>,
+) -> SqResult<T> {
+    if let Some(value) = cache.lock().unwrap().get(path) {
+        return Ok(value.clone());
+    }
+    let value = open(path)?;
+    let mut cache = cache.lock().unwrap();
+    Ok(cache.entry(path.to_path_buf()).or_insert(value).clone())
 }
@@ -0,0 +1,339 @@
+#[cfg(feature = "rayon")]
+use crate::io::extract::{
+    dir_sink, extract_entries, ExtractFilter, ExtractProgress, ExtractReport, ExtractedFile,
+};
+#[cfg(not(feature = "mmap"))]
+use crate::io::index::IndexReader;
+#[cfg(feature = "mmap")]
+use crate::io::mmap::MappedFile;
+use crate::{
+    error::{SqResult, SqpackError},
+    io::{
+        dat::{sqfile::dat_path, SharedDat, SharedDatReader, SqFile},
+        index::{IndexCache, IndexFileEntry},
+    },
+    open_cached,
+    sqpath::Platform,
+    SqPath,
+};
+#[cfg(feature = "mmap")]
+use std::io::Cursor;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// A handle to the game data of an FFXIV installation. Index files are read and cached the first
+/// time a file from their category, expansion and chunk is requested, so repeated lookups do not
+/// rescan the index.
+///
+/// [`open_shared`](#method.open_shared) reads files through .dat handles that are opened once and
+/// shared, so a `SqPack` can be used from many threads without each thread opening its own
+/// handles.
+///
+/// With the `mmap` feature, index files are mapped into memory to be read, and
+/// [`open_mapped`](#method.open_mapped) reads files from .dat files that are mapped once and
+/// shared between all files opened from them.
+///
+/// # Examples
+/// ```no_run
+/// use sqpack::SqPack;
+/// use std::io::Read;
+///
+/// let sqpack = SqPack::new("/home/uwu/ffxiv/game/sqpack");
+/// let mut file = sqpack.open("music/ffxiv/BGM_System_Title.scd").unwrap();
+/// let mut data = Vec::with_capacity(file.total_size());
+/// file.read_to_end(&mut data).unwrap();
+/// ```
+pub struct SqPack {
+    /// The path to the `sqpack` directory of the installation
+    root: PathBuf,
+    /// The platform the installation was built for, which determines the index file names
+    platform: Platform,
+    /// The cached index files, keyed by the path of the index file
+    indexes: Mutex<HashMap<PathBuf, Arc<IndexCache>>>,
+    /// The shared .dat file handles, keyed by the path of the .dat file
+    shared_dats: Mutex<HashMap<PathBuf, SharedDat>>,
+    /// The mapped .dat files, keyed by the path of the .dat file
+    #[cfg(feature = "mmap")]
+    dats: Mutex<HashMap<PathBuf, MappedFile>>,
+}
+
+impl SqPack {
+    /// Creates a new handle over the `sqpack` directory at `root`. No files are read until they
+    /// are requested.
+    pub fn new<P: AsRef<Path>>(root: P) -> SqPack { Self::with_platform(root, Platform::Win32) }
+
+    /// Creates a new handle over the `sqpack` directory at `root` of an installation built for
+    /// `platform`, such as a console build. See [`new`](#method.new).
+    pub fn with_platform<P: AsRef<Path>>(root: P, platform: Platform) -> SqPack {
+        SqPack {
+            root: root.as_ref().to_path_buf(),
+            platform,
+            indexes: Mutex::new(HashMap::new()),
+            shared_dats: Mutex::new(HashMap::new()),
+            #[cfg(feature = "mmap")]
+            dats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the path to the `sqpack` directory this handle reads from.
+    pub fn root(&self) -> &Path { &self.root }
+
+    /// Returns the platform the installation was built for.
+    pub fn platform(&self) -> Platform { self.platform }
+
+    /// Finds the index entry of the file at `sqpath`.
+    ///
+    /// # Returns
+    /// `Err(SqpackError::InvalidSqPath(...))` if the path could not be parsed to find its index
+    /// file, or `Err(SqpackError::SqFileNotFound)` if it is not present in its index file. Paths
+    /// that do not name their chunk are looked up in every chunk of their category and expansion.
+    pub fn entry<SQ: AsRef<SqPath>>(&self, sqpath: SQ) -> SqResult<IndexFileEntry> {
+        self.locate(sqpath.as_ref()).map(|(_, entry)| entry)
+    }
+
+    /// Checks whether the file at `sqpath` is present in the SqPack. Paths that can not be parsed
+    /// are reported as errors rather than as absent.
+    pub fn exists<SQ: AsRef<SqPath>>(&self, sqpath: SQ) -> SqResult<bool> {
+        match self.entry(sqpath) {
+            Ok(_) => Ok(true),
+            Err(SqpackError::SqFileNotFound) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Opens the file at `sqpath` for reading.
+    pub fn open<SQ: AsRef<SqPath>>(&self, sqpath: SQ) -> SqResult<SqFile<File>> {
+        let (index_path, entry) = self.locate(sqpath.as_ref())?;
+        SqFile::open_entry(index_path, entry)
+    }
+
+    /// Opens the file at `sqpath` for reading through a shared handle to its .dat file. The .dat
+    /// file is opened the first time a file within it is opened, and reads from it never block
+    /// each other.
+    pub fn open_shared<SQ: AsRef<SqPath>>(&self, sqpath: SQ) -> SqResult<SqFile<SharedDatReader>> {
+        let (index_path, entry) = self.locate(sqpath.as_ref())?;
+        let dat_path = dat_path(&index_path, entry.dat_file)?;
+        let dat = open_cached(&self.shared_dats, &dat_path, |path| SharedDat::open(path))?;
+        SqFile::open_shared(&dat, entry)
+    }
+
+    /// Opens the file at `sqpath` for reading from its mapped .dat file. The .dat file is mapped
+    /// the first time a file within it is opened.
+    #[cfg(feature = "mmap")]
+    pub fn open_mapped<SQ: AsRef<SqPath>>(
+        &self,
+        sqpath: SQ,
+    ) -> SqResult<SqFile<Cursor<MappedFile>>> {
+        let (index_path, entry) = self.locate(sqpath.as_ref())?;
+        let dat_path = dat_path(&index_path, entry.dat_file)?;
+        let dat = open_cached(&self.dats, &dat_path, |path| MappedFile::open(path))?;
+        SqFile::open_mapped(&dat, entry)
+    }
+
+    /// Reads the entire file at `sqpath` into memory.
+    pub fn read_to_vec<SQ: AsRef<SqPath>>(&self, sqpath: SQ) -> SqResult<Vec<u8>> {
+        let mut file = self.open(sqpath)?;
+        let mut data = Vec::with_capacity(file.total_size());
+        file.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Extracts every file of the index files selected by `filter` in parallel, passing each to
+    /// `sink`. Index files are read through the cache of this handle. Failing to read or store a
+    /// file does not stop the extraction; the failure is recorded in the returned report instead.
+    /// `progress` is called after every file.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use sqpack::{io::extract::ExtractFilter, sqpath::FileType, SqPack};
+    ///
+    /// let sqpack = SqPack::new("/home/uwu/ffxiv/game/sqpack");
+    /// let filter = ExtractFilter {
+    ///     file_type: Some(FileType::EXD),
+    ///     ..ExtractFilter::default()
+    /// };
+    /// let report = sqpack.extract_all_to_dir(filter, "exd", |_| {}).unwrap();
+    /// for failure in &report.failures {
+    ///     eprintln!("{:?}: {}", failure.entry.path_hash, failure.error);
+    /// }
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn extract_all<S, F>(
+        &self,
+        filter: ExtractFilter,
+        sink: S,
+        progress: F,
+    ) -> SqResult<ExtractReport>
+    where
+        S: Fn(ExtractedFile) -> SqResult<()> + Sync,
+        F: Fn(ExtractProgress) + Sync,
+    {
+        let index_paths = self.index_paths(filter)?;
+        let mut caches = Vec::with_capacity(index_paths.len());
+        for index_path in &index_paths {
+            caches.push(self.index(index_path)?);
+        }
+        let indexes = index_paths
+            .iter()
+            .map(PathBuf::as_path)
+            .zip(caches.iter().map(Arc::as_ref))
+            .collect::<Vec<_>>();
+        Ok(extract_entries(
+            &indexes,
+            |path| open_cached(&self.shared_dats, path, |path| SharedDat::open(path)),
+            sink,
+            progress,
+        ))
+    }
+
+    /// Extracts every file of the index files selected by `filter` into `out_dir`, at the paths
+    /// given by [`ExtractedFile::hashed_path`](io/extract/struct.ExtractedFile.html#method.hashed_path).
+    /// See [`extract_all`](#method.extract_all).
+    #[cfg(feature = "rayon")]
+    pub fn extract_all_to_dir<D, F>(
+        &self,
+        filter: ExtractFilter,
+        out_dir: D,
+        progress: F,
+    ) -> SqResult<ExtractReport>
+    where
+        D: AsRef<Path>,
+        F: Fn(ExtractProgress) + Sync,
+    {
+        self.extract_all(filter, dir_sink(out_dir.as_ref()), progress)
+    }
+
+    /// Lists the index files of this handle's platform that are selected by `filter`, in order.
+    #[cfg(feature = "rayon")]
+    fn index_paths(&self, filter: ExtractFilter) -> SqResult<Vec<PathBuf>> {
+        let mut index_paths = Vec::new();
+        for dir in std::fs::read_dir(&self.root)? {
+            let dir = dir?;
+            if !dir.file_type()?.is_dir() {
+                continue;
+            }
+            for file in std::fs::read_dir(dir.path())? {
+                let file = file?;
+                let selected = file
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|name| filter.matches(name, self.platform));
+                if selected {
+                    index_paths.push(file.path());
+                }
+            }
+        }
+        index_paths.sort();
+        Ok(index_paths)
+    }
+
+    /// Finds the index file that contains `sqpath` and its entry within it. Paths that do not name
+    /// their chunk are looked up in every chunk of their category and expansion.
+    fn locate(&self, sqpath: &SqPath) -> SqResult<(PathBuf, IndexFileEntry)> {
+        let parsed = sqpath.parse()?;
+        for index_path in parsed.index_paths(&self.root, self.platform)? {
+            if let Some(entry) = self.index(&index_path)?.get_hash(parsed.sq_index_hash()) {
+                return Ok((index_path, *entry));
+            }
+        }
+        Err(SqpackError::SqFileNotFound)
+    }
+
+    /// Retrieves the cached index file at `index_path`, reading it if it was not yet cached.
+    fn index(&self, index_path: &Path) -> SqResult<Arc<IndexCache>> {
+        open_cached(&self.indexes, index_path, |path| {
+            Self::read_index(path).map(Arc::new)
+        })
+    }
+
+    /// Reads the index file at `index_path` into a cache.
+    #[cfg(not(feature = "mmap"))]
+    fn read_index(index_path: &Path) -> SqResult<IndexCache> {
+        let mut reader = IndexReader::new(File::open(index_path)?)?;
+        IndexCache::from_reader(&mut reader)
+    }
+
+    /// Reads the index file at `index_path` into a cache, mapping it into memory to read it.
+    #[cfg(feature = "mmap")]
+    fn read_index(index_path: &Path) -> SqResult<IndexCache> {
+        IndexCache::from_mapped(&MappedFile::open(index_path)?)
+    }
+}
+
+#[cfg(test)]
+mod sqpack_tests {
+    use crate::{
+        error::SqpackError,
+        io::{
+            dat::SqFile,
+            index::{IndexCache, IndexReader},
+        },
+        sqpath::Platform,
+        test_util::TempSqPack,
+        SqPack,
+    };
+    use std::{fs::File, io::Read};
+
+    #[test]
+    fn finds_paths_without_chunks() {
+        let temp = TempSqPack::new("chunks");
+        temp.write(
+            "ex1/020101.win32.index",
+            &[("bg/ex1/01_abr_a2/level/bg.lgb", b"zone")],
+        );
+        temp.write(
+            "ex1/020103.win32.index",
+            &[("bg/ex1/shared/level/bg.lgb", b"shared")],
+        );
+
+        let sqpack = SqPack::new(temp.root());
+        assert_eq!(
+            sqpack.read_to_vec("bg/ex1/01_abr_a2/level/bg.lgb").unwrap(),
+            b"zone"
+        );
+        assert_eq!(
+            sqpack.read_to_vec("bg/ex1/shared/level/bg.lgb").unwrap(),
+            b"shared"
+        );
+        assert!(!sqpack.exists("bg/ex1/shared/level/missing.lgb").unwrap());
+        // a path naming its chunk is only looked up in that chunk, which does not exist here
+        assert!(sqpack.exists("bg/ex1/02_abr_a3/level/bg.lgb").is_err());
+
+        let index_path = temp.root().join("ex1/020103.win32.index");
+        let cache = IndexCache::from_reader(
+            &mut IndexReader::new(File::open(index_path).unwrap()).unwrap(),
+        )
+        .unwrap();
+        let mut data = Vec::new();
+        SqFile::open_sqpath_cached("bg/ex1/shared/level/bg.lgb", temp.root(), &cache)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"shared");
+    }
+
+    #[test]
+    fn finds_paths_of_platform() {
+        let temp = TempSqPack::new("platform-chunks");
+        temp.write(
+            "ex2/020204.ps4.index",
+            &[("bg/ex2/shared/level/bg.lgb", b"ps4")],
+        );
+
+        let mut data = Vec::new();
+        SqFile::open_sqpath_for("bg/ex2/shared/level/bg.lgb", temp.root(), Platform::PS4)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"ps4");
+        assert!(matches!(
+            SqFile::open_sqpath("bg/ex2/shared/level/bg.lgb", temp.root()),
+            Err(SqpackError::SqFileNotFound)
+        ));
+    }
+}
@@ -1,6 +1,11 @@
-use crate::hash;
+use crate::{
+    error::{SqPathError, SqResult},
+    hash,
+};
 use std::{
-    borrow::Borrow,
+    borrow::{Borrow, Cow},
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs,
     ops::Deref,
     path::{Path, PathBuf},
 };
@@ -52,57 +57,234 @@ impl SqPath {
         })
     }
 
+    /// Gets the hash of the full path of the file, as used by `.index2` files. Unlike
+    /// [`sq_index_hash`](#method.sq_index_hash), this is always computable, though it does not
+    /// verify that the path is well-formed.
+    ///
+    /// # Examples
+    /// ```
+    /// use sqpack::SqPath;
+    /// let hash = SqPath::new("music/ffxiv/BGM_System_Title.scd").sq_index2_hash();
+    /// assert_eq!(hash, 0xE09DBB74);
+    /// ```
+    pub fn sq_index2_hash(&self) -> u32 { hash::compute_str_lower(&self.inner) }
+
+    /// Parses the parts of this path that locate it within the SqPack: its category, expansion and
+    /// chunk, which select its index file, and its folder and file name, which are hashed to find
+    /// it within the index file. Paths whose second segment is not an expansion, such as
+    /// `chara/equipment/...` or `exd/root.exl`, belong to the base game. Only `bg` paths of
+    /// expansions are split into chunks, named by the prefix of their third segment as in
+    /// `bg/ex1/01_abr_a2/...`; if that prefix or segment is missing, the chunk is left unknown.
+    ///
+    /// # Returns
+    /// `Err(...)` naming the part of the path that is missing or invalid.
+    ///
+    /// # Examples
+    /// ```
+    /// use sqpack::{error::SqPathError, sqpath::{Expansion, FileType}, SqPath};
+    ///
+    /// let parsed = SqPath::new("music/ex3/BGM_EX3_Event_05.scd").parse().unwrap();
+    /// assert_eq!(parsed.file_type, FileType::Music);
+    /// assert_eq!(parsed.expansion, Expansion::Shadowbringers);
+    /// assert_eq!(parsed.folder, "music/ex3");
+    /// assert_eq!(parsed.file_name, "BGM_EX3_Event_05.scd");
+    ///
+    /// let err = SqPath::new("mucis/ffxiv/BGM_System_Title.scd").parse().unwrap_err();
+    /// assert_eq!(err, SqPathError::UnknownCategory("mucis".to_string()));
+    /// ```
+    pub fn parse(&self) -> Result<ParsedSqPath<'_>, SqPathError> {
+        let (folder, file_name) = self.inner.rsplit_once('/').ok_or(SqPathError::NoFolder)?;
+        if file_name.is_empty() {
+            return Err(SqPathError::NoFileName);
+        }
+
+        let mut segments = folder.split('/');
+        let category = segments.next().unwrap_or_default();
+        let file_type = FileType::parse(category)
+            .ok_or_else(|| SqPathError::UnknownCategory(category.to_string()))?;
+        // only folders named after an expansion are stored outside of the base game, as in
+        // `music/ex1/...`, while most categories use their second segment for something else, as
+        // in `chara/equipment/...`
+        let expansion = segments
+            .next()
+            .and_then(Expansion::parse)
+            .unwrap_or(Expansion::FFXIV);
+        // a file directly inside `bg/exN` has no zone folder, so its name says nothing of its chunk
+        let chunk = SqPackNumber::parse(file_type, expansion, segments.next());
+
+        Ok(ParsedSqPath {
+            file_type,
+            expansion,
+            chunk,
+            folder,
+            file_name,
+        })
+    }
+
     /// Gets the path to the index file that locates this SqPath within the .dat files. The location
     /// of the SqPack currently in use is specified by `sqpack`
     ///
     /// # Returns
-    /// An Option of an OS `PathBuf` pointing to the index file if the proper index file could be
-    /// parsed, None otherwise.
-    pub fn sqpack_index_path<P: AsRef<Path>>(&self, sqpack: P) -> Option<PathBuf> {
-        let sqpack = sqpack.as_ref();
-
-        // "______.win32.index"
-        let mut data: [u8; 18] = [
-            0, 0, 0, 0, 0, 0, 0x2e, 0x77, 0x69, 0x6e, 0x33, 0x32, 0x2e, 0x69, 0x6e, 0x64, 0x65,
-            0x78,
-        ];
+    /// An OS `PathBuf` pointing to the index file if the proper index file could be parsed, or the
+    /// reason it could not be, as with [`parse`](#method.parse). Paths that do not name their
+    /// chunk return `Err(SqPathError::NoChunk)`, see
+    /// [`ParsedSqPath::index_paths`](struct.ParsedSqPath.html#method.index_paths) to find them.
+    pub fn sqpack_index_path<P: AsRef<Path>>(&self, sqpack: P) -> Result<PathBuf, SqPathError> {
+        self.sqpack_index_path_for(sqpack, Platform::Win32)
+    }
+
+    /// Gets the path to the index file that locates this SqPath within the .dat files of a SqPack
+    /// built for `platform`. See [`sqpack_index_path`](#method.sqpack_index_path).
+    pub fn sqpack_index_path_for<P: AsRef<Path>>(
+        &self,
+        sqpack: P,
+        platform: Platform,
+    ) -> Result<PathBuf, SqPathError> {
+        self.parse()?.sqpack_index_path(sqpack, platform)
+    }
+
+    /// Gets the path to the `.index2` file that locates this SqPath within the .dat files. See
+    /// [`sqpack_index_path`](#method.sqpack_index_path).
+    pub fn sqpack_index2_path<P: AsRef<Path>>(&self, sqpack: P) -> Result<PathBuf, SqPathError> {
+        self.sqpack_index2_path_for(sqpack, Platform::Win32)
+    }
 
-        let file_type = FileType::parse_from_sqpath(self).map(|a| a.file_name_prefix_str());
-        file_type
-            .and_then(|file| {
-                let file_slice = &mut data[0..2];
-                let bytes = file.as_bytes();
-                assert_eq!(bytes.len(), 2);
-                file_slice[0] = bytes[0];
-                file_slice[1] = bytes[1];
-
-                Expansion::parse_from_sqpath(self).map(|a| a.file_name_prefix_str())
-            })
-            .and_then(|exp| {
-                let expansion_slice = &mut data[2..4];
-                let bytes = exp.as_bytes();
-                assert_eq!(bytes.len(), 2);
-                expansion_slice[0] = bytes[0];
-                expansion_slice[1] = bytes[1];
-
-                SqPackNumber::parse_from_sqpath(self).map(|a| a.file_name_prefix_str())
-            })
-            .and_then(|num| {
-                let number_slice = &mut data[4..6];
-                number_slice[0] = num[0];
-                number_slice[1] = num[1];
-
-                // Always valid utf-8 at this point
-                let file_name = std::str::from_utf8(data.as_ref()).unwrap();
-                let pb = sqpack
-                    .join(Expansion::parse_from_sqpath(self).unwrap().as_str())
-                    .join(file_name);
-                Some(pb)
-            })
+    /// Gets the path to the `.index2` file that locates this SqPath within the .dat files of a
+    /// SqPack built for `platform`. See [`sqpack_index_path`](#method.sqpack_index_path).
+    pub fn sqpack_index2_path_for<P: AsRef<Path>>(
+        &self,
+        sqpack: P,
+        platform: Platform,
+    ) -> Result<PathBuf, SqPathError> {
+        self.sqpack_index_path_for(sqpack, platform)
+            .map(|path| path.with_extension("index2"))
     }
 
     /// Returns this path as a reference to a string
     pub fn as_str(&self) -> &str { &self.inner }
+
+    /// Checks whether this path is already in the form produced by
+    /// [`SqPathBuf::normalized`](struct.SqPathBuf.html#method.normalized).
+    ///
+    /// # Examples
+    /// ```
+    /// use sqpack::SqPath;
+    /// assert!(SqPath::new("music/ffxiv/bgm_system_title.scd").is_normalized());
+    /// assert!(!SqPath::new("music/ffxiv/BGM_System_Title.scd").is_normalized());
+    /// assert!(!SqPath::new("/music\\ffxiv//bgm_system_title.scd").is_normalized());
+    /// ```
+    pub fn is_normalized(&self) -> bool {
+        let path = &self.inner;
+        path.trim() == path
+            && !path.starts_with('/')
+            && !path.ends_with('/')
+            && !path.contains("//")
+            && !path.bytes().any(|b| b == b'\\' || b.is_ascii_uppercase())
+    }
+
+    /// Returns the folder containing this path, which is everything before the last `/`.
+    ///
+    /// # Returns
+    /// `None` if the path has no folder.
+    ///
+    /// # Examples
+    /// ```
+    /// use sqpack::SqPath;
+    /// let path = SqPath::new("music/ffxiv/BGM_System_Title.scd");
+    /// assert_eq!(path.parent(), Some(SqPath::new("music/ffxiv")));
+    /// assert_eq!(SqPath::new("music").parent(), None);
+    /// ```
+    pub fn parent(&self) -> Option<&SqPath> {
+        self.inner
+            .rfind('/')
+            .map(|index| SqPath::new(&self.inner[..index]))
+    }
+
+    /// Returns the final component of this path, which is everything after the last `/`.
+    ///
+    /// # Returns
+    /// `None` if the path is empty or ends with a `/`.
+    ///
+    /// # Examples
+    /// ```
+    /// use sqpack::SqPath;
+    /// let path = SqPath::new("music/ffxiv/BGM_System_Title.scd");
+    /// assert_eq!(path.file_name(), Some("BGM_System_Title.scd"));
+    /// ```
+    pub fn file_name(&self) -> Option<&str> {
+        let name = match self.inner.rfind('/') {
+            Some(index) => &self.inner[index + 1..],
+            None => &self.inner,
+        };
+        (!name.is_empty()).then_some(name)
+    }
+
+    /// Returns the file name without its extension. A file name starting with its only `.` has no
+    /// extension, so the whole name is returned.
+    ///
+    /// # Examples
+    /// ```
+    /// use sqpack::SqPath;
+    /// let path = SqPath::new("chara/equipment/e0001/model/c0201e0001_top.mdl");
+    /// assert_eq!(path.file_stem(), Some("c0201e0001_top"));
+    /// ```
+    pub fn file_stem(&self) -> Option<&str> {
+        let name = self.file_name()?;
+        Some(split_extension(name).0)
+    }
+
+    /// Returns the extension of the file name, which is everything after the last `.`.
+    ///
+    /// # Returns
+    /// `None` if the file name has no extension. See [`file_stem`](#method.file_stem).
+    ///
+    /// # Examples
+    /// ```
+    /// use sqpack::SqPath;
+    /// let path = SqPath::new("ui/icon/000000/000001_hr1.tex");
+    /// assert_eq!(path.extension(), Some("tex"));
+    /// ```
+    pub fn extension(&self) -> Option<&str> { split_extension(self.file_name()?).1 }
+
+    /// Iterates over the components of this path, which are separated by `/`. Empty components,
+    /// such as those from repeated separators, are skipped.
+    ///
+    /// # Examples
+    /// ```
+    /// use sqpack::SqPath;
+    /// let path = SqPath::new("music/ffxiv/BGM_System_Title.scd");
+    /// let components = path.components().collect::<Vec<_>>();
+    /// assert_eq!(components, ["music", "ffxiv", "BGM_System_Title.scd"]);
+    /// ```
+    pub fn components(&self) -> impl DoubleEndedIterator<Item = &str> + '_ {
+        self.inner
+            .split('/')
+            .filter(|component| !component.is_empty())
+    }
+
+    /// Creates an owned path with `path` appended to this one. See
+    /// [`SqPathBuf::push`](struct.SqPathBuf.html#method.push).
+    ///
+    /// # Examples
+    /// ```
+    /// use sqpack::SqPath;
+    /// let path = SqPath::new("music/ffxiv").join("BGM_System_Title.scd");
+    /// assert_eq!(path.as_str(), "music/ffxiv/BGM_System_Title.scd");
+    /// ```
+    pub fn join<SQ: AsRef<SqPath>>(&self, path: SQ) -> SqPathBuf {
+        let mut buf = self.to_owned();
+        buf.push(path);
+        buf
+    }
+}
+
+/// Splits a file name into its stem and extension at the last `.`, unless that is the first
+/// character of the name.
+fn split_extension(name: &str) -> (&str, Option<&str>) {
+    match name.rfind('.') {
+        Some(index) if index > 0 => (&name[..index], Some(&name[index + 1..])),
+        _ => (name, None),
+    }
 }
 
 #[derive(Ord, PartialOrd, PartialEq, Eq, Debug, Hash, Clone)]
@@ -133,6 +315,108 @@ impl SqPathBuf {
             inner: String::from(s.as_ref()),
         }
     }
+
+    /// Creates a new SqPathBuf in the form the game uses for paths: surrounding whitespace is
+    /// trimmed, `\\` separators become `/`, leading, trailing and repeated separators are removed
+    /// and ASCII letters are lowercased. See [`SqPath::is_normalized`](struct.SqPath.html#method.is_normalized).
+    ///
+    /// # Examples
+    /// ```
+    /// use sqpack::sqpath::SqPathBuf;
+    /// let path = SqPathBuf::normalized(" /Music\\FFXIV\\BGM_System_Title.scd\n");
+    /// assert_eq!(path.as_str(), "music/ffxiv/bgm_system_title.scd");
+    /// ```
+    pub fn normalized<S: AsRef<str> + ?Sized>(s: &S) -> SqPathBuf {
+        let mut inner = String::with_capacity(s.as_ref().len());
+        let components = s
+            .as_ref()
+            .trim()
+            .split(['/', '\\'])
+            .filter(|component| !component.is_empty());
+        for component in components {
+            if !inner.is_empty() {
+                inner.push('/');
+            }
+            inner.push_str(component);
+        }
+        inner.make_ascii_lowercase();
+        SqPathBuf { inner }
+    }
+
+    /// Appends `path` to this path, separated by a `/` unless this path is empty or already ends
+    /// with one.
+    ///
+    /// # Examples
+    /// ```
+    /// use sqpack::sqpath::SqPathBuf;
+    /// let mut path = SqPathBuf::new("chara/equipment");
+    /// path.push(format!("e{:04}", 1));
+    /// path.push("model/c0201e0001_top.mdl");
+    /// assert_eq!(path.as_str(), "chara/equipment/e0001/model/c0201e0001_top.mdl");
+    /// ```
+    pub fn push<SQ: AsRef<SqPath>>(&mut self, path: SQ) {
+        if !self.inner.is_empty() && !self.inner.ends_with('/') {
+            self.inner.push('/');
+        }
+        self.inner.push_str(path.as_ref().as_str());
+    }
+
+    /// Truncates this path to its [`parent`](struct.SqPath.html#method.parent).
+    ///
+    /// # Returns
+    /// `false` if the path has no folder, in which case it is left unchanged.
+    pub fn pop(&mut self) -> bool {
+        match self.inner.rfind('/') {
+            Some(index) => {
+                self.inner.truncate(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replaces the extension of the file name with `extension`, or removes it if `extension` is
+    /// empty.
+    ///
+    /// # Returns
+    /// `false` if the path has no file name, in which case it is left unchanged.
+    ///
+    /// # Examples
+    /// ```
+    /// use sqpack::sqpath::SqPathBuf;
+    /// let mut path = SqPathBuf::new("ui/icon/000000/000001.tex");
+    /// path.set_extension("png");
+    /// assert_eq!(path.as_str(), "ui/icon/000000/000001.png");
+    /// ```
+    pub fn set_extension<S: AsRef<str>>(&mut self, extension: S) -> bool {
+        let Some(name) = self.file_name() else {
+            return false;
+        };
+        let stem_end = self.inner.len() - name.len() + split_extension(name).0.len();
+        self.inner.truncate(stem_end);
+        let extension = extension.as_ref();
+        if !extension.is_empty() {
+            self.inner.push('.');
+            self.inner.push_str(extension);
+        }
+        true
+    }
+}
+
+impl From<String> for SqPathBuf {
+    fn from(inner: String) -> Self { SqPathBuf { inner } }
+}
+
+impl From<&str> for SqPathBuf {
+    fn from(s: &str) -> Self { SqPathBuf::new(s) }
+}
+
+impl Display for SqPath {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult { f.write_str(&self.inner) }
+}
+
+impl Display for SqPathBuf {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult { f.write_str(&self.inner) }
 }
 
 impl Deref for SqPathBuf {
@@ -150,6 +434,154 @@ pub struct SqIndexHash {
     pub file_hash: u32,
 }
 
+impl SqIndexHash {
+    /// Computes the hash of `path`, splitting it into a folder and file name at the last `/`. This
+    /// can be used in constants, see also the [`sqhash!`](../macro.sqhash.html) macro.
+    ///
+    /// # Returns
+    /// `None` if the path has no folder, as with
+    /// [`SqPath::sq_index_hash`](struct.SqPath.html#method.sq_index_hash).
+    pub const fn of(path: &str) -> Option<SqIndexHash> {
+        let bytes = path.as_bytes();
+        let mut index = bytes.len();
+        while index > 0 {
+            index -= 1;
+            if bytes[index] == b'/' {
+                let (folder, file) = bytes.split_at(index);
+                let (_, file) = file.split_at(1);
+                return Some(SqIndexHash {
+                    folder_hash: hash::compute_const(hash::FFXIV_SEED, folder, true),
+                    file_hash: hash::compute_const(hash::FFXIV_SEED, file, true),
+                });
+            }
+        }
+        None
+    }
+}
+
+/// The parts of a [`SqPath`](struct.SqPath.html), as returned by
+/// [`SqPath::parse`](struct.SqPath.html#method.parse)
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct ParsedSqPath<'a> {
+    /// The category of the path, which is its first segment
+    pub file_type: FileType,
+
+    /// The expansion of the path, which is its second segment
+    pub expansion: Expansion,
+
+    /// The chunk of the category the path is stored in, or `None` if the path does not name it
+    /// and it may be stored in any chunk of its category and expansion
+    pub chunk: Option<SqPackNumber>,
+
+    /// Everything before the last `/` of the path
+    pub folder: &'a str,
+
+    /// Everything after the last `/` of the path
+    pub file_name: &'a str,
+}
+
+impl ParsedSqPath<'_> {
+    /// Returns the name of the index file the path is stored in, such as `0c0300.win32.index`.
+    ///
+    /// # Returns
+    /// `Err(SqPathError::NoChunk)` if the path does not name its chunk.
+    pub fn index_file_name(&self, platform: Platform) -> Result<String, SqPathError> {
+        let chunk = self.chunk.ok_or(SqPathError::NoChunk)?;
+        Ok(format!(
+            "{}{:02x}.{}.index",
+            self.chunk_prefix(),
+            chunk.number(),
+            platform.as_str()
+        ))
+    }
+
+    /// Returns the path to the index file the path is stored in, within the `sqpack` directory of
+    /// an installation built for `platform`.
+    ///
+    /// # Returns
+    /// `Err(SqPathError::NoChunk)` if the path does not name its chunk.
+    pub fn sqpack_index_path<P: AsRef<Path>>(
+        &self,
+        sqpack: P,
+        platform: Platform,
+    ) -> Result<PathBuf, SqPathError> {
+        Ok(sqpack
+            .as_ref()
+            .join(&*self.expansion.as_str())
+            .join(self.index_file_name(platform)?))
+    }
+
+    /// Returns the paths to the index files the path may be stored in, within the `sqpack`
+    /// directory of an installation built for `platform`. This is the single index file of its
+    /// chunk if the path names it, otherwise every index file of its category and expansion found
+    /// in the directory, in order of their chunk.
+    pub fn index_paths<P: AsRef<Path>>(
+        &self,
+        sqpack: P,
+        platform: Platform,
+    ) -> SqResult<Vec<PathBuf>> {
+        if self.chunk.is_some() {
+            return Ok(vec![self.sqpack_index_path(sqpack, platform)?]);
+        }
+
+        let prefix = self.chunk_prefix();
+        let suffix = format!(".{}.index", platform.as_str());
+        let mut index_paths = Vec::new();
+        for file in fs::read_dir(sqpack.as_ref().join(&*self.expansion.as_str()))? {
+            let file = file?;
+            let name = file.file_name();
+            let chunk = name
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|name| name.strip_suffix(&suffix));
+            let is_chunk =
+                |chunk: &str| chunk.len() == 2 && chunk.bytes().all(|b| b.is_ascii_hexdigit());
+            if chunk.is_some_and(is_chunk) {
+                index_paths.push(file.path());
+            }
+        }
+        index_paths.sort();
+        Ok(index_paths)
+    }
+
+    /// The start of the names of the index files of the path's category and expansion, such as
+    /// `0c03`.
+    fn chunk_prefix(&self) -> String {
+        format!(
+            "{}{}",
+            self.file_type.file_name_prefix_str(),
+            self.expansion.file_name_prefix_str()
+        )
+    }
+
+    /// Computes the hash of the path as stored in `.index` files.
+    pub fn sq_index_hash(&self) -> SqIndexHash {
+        SqIndexHash {
+            folder_hash: hash::compute_str_lower(self.folder),
+            file_hash: hash::compute_str_lower(self.file_name),
+        }
+    }
+}
+
+/// Every FileType, in the order of their index file prefixes
+const FILE_TYPES: [FileType; 15] = [
+    FileType::Common,
+    FileType::BGCommon,
+    FileType::BG,
+    FileType::Cut,
+    FileType::Chara,
+    FileType::Shader,
+    FileType::UI,
+    FileType::Sound,
+    FileType::VFX,
+    FileType::UIScript,
+    FileType::EXD,
+    FileType::GameScript,
+    FileType::Music,
+    FileType::SqpackTest,
+    FileType::Debug,
+];
+
 /// The FileType of a SqPath. Specifically, not the actual file type, but rather
 /// the index file it can be found in, which are grouped by broad categories of files.
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
@@ -178,30 +610,16 @@ impl FileType {
     /// An option containing the variant corresponding to the file type, or `None`
     /// if the file type was unrecognized, or if the path was malformed.
     pub fn parse_from_sqpath<P: AsRef<SqPath>>(sqpath: P) -> Option<FileType> {
-        let sqpath = sqpath.as_ref();
-        let s = sqpath.as_str();
+        let s = sqpath.as_ref().as_str();
+        s.find('/').and_then(|index| FileType::parse(&s[..index]))
+    }
 
-        let index_opt = s.find('/');
-        let slice_opt = index_opt.map(|index| &s[..index]);
-
-        slice_opt.and_then(|type_str| match type_str {
-            "common" => Some(FileType::Common),
-            "bgcommon" => Some(FileType::BGCommon),
-            "bg" => Some(FileType::BG),
-            "cut" => Some(FileType::Cut),
-            "chara" => Some(FileType::Chara),
-            "shader" => Some(FileType::Shader),
-            "ui" => Some(FileType::UI),
-            "sound" => Some(FileType::Sound),
-            "vfx" => Some(FileType::VFX),
-            "ui_script" => Some(FileType::UIScript),
-            "exd" => Some(FileType::EXD),
-            "game_script" => Some(FileType::GameScript),
-            "music" => Some(FileType::Music),
-            "_sqpack_test" => Some(FileType::SqpackTest),
-            "_debug" => Some(FileType::Debug),
-            _ => None,
-        })
+    /// Parses a file type from its category name, the first segment of a SqPath, ignoring case as
+    /// the game does.
+    fn parse(type_str: &str) -> Option<FileType> {
+        FILE_TYPES
+            .into_iter()
+            .find(|file_type| file_type.as_str().eq_ignore_ascii_case(type_str))
     }
 
     /// Gets a reference to a static string representing the hex code of the FileType variant.
@@ -271,6 +689,9 @@ impl FileType {
 }
 
 /// The game expansion a file can be found in, such as FFXIV (base game), EX1 (Heavensward), etc...
+///
+/// Expansions newer than this crate are represented by `ExN`, which can only be constructed with
+/// [`from_number`](#method.from_number), so that known expansions always use their named variant.
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug, Hash)]
 pub enum Expansion {
     FFXIV,
@@ -278,9 +699,47 @@ pub enum Expansion {
     Stormblood,
     Shadowbringers,
     Endwalker,
+    Dawntrail,
+    /// An expansion without a named variant, identified by its number (`exN`)
+    ExN(UnnamedExpansion),
+}
+
+/// The number of an expansion newer than the named variants of [`Expansion`](enum.Expansion.html)
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug, Hash)]
+pub struct UnnamedExpansion(u8);
+
+impl UnnamedExpansion {
+    /// Returns the number of this expansion, `N` in `exN`.
+    pub fn number(&self) -> u8 { self.0 }
 }
 
 impl Expansion {
+    /// Creates the expansion with the given number, where 0 is the base game and `N` is `exN`.
+    pub fn from_number(number: u8) -> Expansion {
+        match number {
+            0 => Expansion::FFXIV,
+            1 => Expansion::Heavensward,
+            2 => Expansion::Stormblood,
+            3 => Expansion::Shadowbringers,
+            4 => Expansion::Endwalker,
+            5 => Expansion::Dawntrail,
+            n => Expansion::ExN(UnnamedExpansion(n)),
+        }
+    }
+
+    /// Returns the number of this expansion, where 0 is the base game and `N` is `exN`.
+    pub fn number(&self) -> u8 {
+        match self {
+            Expansion::FFXIV => 0,
+            Expansion::Heavensward => 1,
+            Expansion::Stormblood => 2,
+            Expansion::Shadowbringers => 3,
+            Expansion::Endwalker => 4,
+            Expansion::Dawntrail => 5,
+            Expansion::ExN(unnamed) => unnamed.number(),
+        }
+    }
+
     /// Parses the expansion implied by the second segment of `sqpath`
     ///
     /// # Returns
@@ -290,78 +749,178 @@ impl Expansion {
         let sqpath = sqpath.as_ref();
         let s = sqpath.as_str();
 
-        s.split('/')
-            .skip(1)
-            .next()
-            .and_then(|exp_str| match exp_str {
-                "ffxiv" => Some(Expansion::FFXIV),
-                "ex1" => Some(Expansion::Heavensward),
-                "ex2" => Some(Expansion::Stormblood),
-                "ex3" => Some(Expansion::Shadowbringers),
-                "ex4" => Some(Expansion::Endwalker),
-                _ => None,
-            })
+        s.split('/').nth(1).and_then(Expansion::parse)
     }
 
-    /// Gets a reference to a static string representing the hex code of the Expansion variant.
-    /// This hex code is part of what composes a file name in the sqpack, i.e. music .index and .dat
-    /// from Heavensward might be `0c0100.win32.index/dat`.
-    pub fn file_name_prefix_str(&self) -> &'static str {
+    /// Parses an expansion from its directory name, `ffxiv` or `exN`, ignoring case as the game
+    /// does.
+    fn parse(exp_str: &str) -> Option<Expansion> {
+        if exp_str.eq_ignore_ascii_case("ffxiv") {
+            return Some(Expansion::FFXIV);
+        }
+        let number = exp_str
+            .get(..2)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("ex"))
+            .and(exp_str.get(2..))?;
+        // only accept the canonical form, i.e. not "ex01" or "ex+1"
+        if number.starts_with('0') || !number.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        number.parse().ok().map(Expansion::from_number)
+    }
+
+    /// Gets a string representing the hex code of the Expansion variant. This hex code is part of
+    /// what composes a file name in the sqpack, i.e. music .index and .dat from Heavensward might
+    /// be `0c0100.win32.index/dat`. Only expansions without a named variant allocate.
+    ///
+    /// # Compatibility
+    /// This returned `&'static str` before 0.2. It is a `Cow` so that `ExN` can be formatted on
+    /// demand; callers that need a `&str` can deref it.
+    pub fn file_name_prefix_str(&self) -> Cow<'static, str> {
         match self {
-            Expansion::FFXIV => "00",
-            Expansion::Heavensward => "01",
-            Expansion::Stormblood => "02",
-            Expansion::Shadowbringers => "03",
-            Expansion::Endwalker => "04",
+            Expansion::FFXIV => "00".into(),
+            Expansion::Heavensward => "01".into(),
+            Expansion::Stormblood => "02".into(),
+            Expansion::Shadowbringers => "03".into(),
+            Expansion::Endwalker => "04".into(),
+            Expansion::Dawntrail => "05".into(),
+            Expansion::ExN(unnamed) => format!("{:02x}", unnamed.number()).into(),
         }
     }
 
     /// Gets a byte representing the hex code of the Expansion variant. See `file_name_prefix_str`.
-    pub fn file_name_prefix(&self) -> u8 {
+    pub fn file_name_prefix(&self) -> u8 { self.number() }
+
+    /// Returns the directory name of this variant. Useful in composing SqPaths. Only expansions
+    /// without a named variant allocate.
+    ///
+    /// # Compatibility
+    /// This returned `&'static str` before 0.2, see
+    /// [`file_name_prefix_str`](#method.file_name_prefix_str).
+    pub fn as_str(&self) -> Cow<'static, str> {
         match self {
-            Expansion::FFXIV => 0x00u8,
-            Expansion::Heavensward => 0x01u8,
-            Expansion::Stormblood => 0x02u8,
-            Expansion::Shadowbringers => 0x03u8,
-            Expansion::Endwalker => 0x04u8,
+            Expansion::FFXIV => "ffxiv".into(),
+            Expansion::Heavensward => "ex1".into(),
+            Expansion::Stormblood => "ex2".into(),
+            Expansion::Shadowbringers => "ex3".into(),
+            Expansion::Endwalker => "ex4".into(),
+            Expansion::Dawntrail => "ex5".into(),
+            Expansion::ExN(unnamed) => format!("ex{}", unnamed.number()).into(),
         }
     }
+}
 
-    /// Returns a static str representation of this variant. Useful in composing SqPaths.
+/// The platform a SqPack was built for. This determines the suffix of the index and .dat file
+/// names, such as `0c0000.ps3.index`, and the byte order of the values within them.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug, Hash, Default)]
+pub enum Platform {
+    #[default]
+    Win32,
+    PS3,
+    PS4,
+    PS5,
+}
+
+impl Platform {
+    /// Gets the platform identified by `id` in a SqPack header, or `None` if it is unknown.
+    pub fn from_id(id: u8) -> Option<Platform> {
+        match id {
+            0 => Some(Platform::Win32),
+            1 => Some(Platform::PS3),
+            2 => Some(Platform::PS4),
+            3 => Some(Platform::PS5),
+            _ => None,
+        }
+    }
+
+    /// Returns the ID identifying this platform in a SqPack header.
+    pub fn id(&self) -> u8 {
+        match self {
+            Platform::Win32 => 0,
+            Platform::PS3 => 1,
+            Platform::PS4 => 2,
+            Platform::PS5 => 3,
+        }
+    }
+
+    /// Returns the suffix of the index and .dat file names of this platform, such as `win32`.
     pub fn as_str(&self) -> &'static str {
         match self {
-            Expansion::FFXIV => "ffxiv",
-            Expansion::Heavensward => "ex1",
-            Expansion::Stormblood => "ex2",
-            Expansion::Shadowbringers => "ex3",
-            Expansion::Endwalker => "ex4",
+            Platform::Win32 => "win32",
+            Platform::PS3 => "ps3",
+            Platform::PS4 => "ps4",
+            Platform::PS5 => "ps5",
         }
     }
+
+    /// Whether the values within files of this platform are stored big-endian.
+    pub fn is_big_endian(&self) -> bool { *self == Platform::PS3 }
 }
 
 #[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Debug, Hash, Default)]
 pub struct SqPackNumber(u8);
 
+/// How the files of a category are split between the chunks of an expansion
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum ChunkRule {
+    /// Every file is stored in chunk 0
+    Single,
+    /// Each zone is stored in the chunk named by the two digit hex prefix of its folder, which is
+    /// the third segment of the path, as in `bg/ex1/01_abr_a2/...`
+    ZonePrefix,
+}
+
+/// The chunk rules of the categories stored in expansions, following the index files the game
+/// installs. The `ffxiv` folder holds a single `XX0000` index for each category, while each `exN`
+/// folder holds a `02NNZZ` index for every zone prefix `ZZ` of its levels, next to a single
+/// `03NN00` for its cutscenes and `0cNN00` for its music. Categories missing here are kept in
+/// chunk 0.
+const EXPANSION_CHUNK_RULES: [(FileType, ChunkRule); 3] = [
+    (FileType::BG, ChunkRule::ZonePrefix),
+    (FileType::Cut, ChunkRule::Single),
+    (FileType::Music, ChunkRule::Single),
+];
+
 impl SqPackNumber {
-    /// Parses the numerical index of the specific index/dat file implied by the filename of `sqpath`
+    /// Parses the numerical index of the specific index/dat file implied by `sqpath`. See
+    /// [`SqPath::parse`](struct.SqPath.html#method.parse) for how the game assigns chunks.
     ///
     /// # Returns
     /// An option containing numerical index of the dat/index file, or `None`
-    /// if the path was malformed.
+    /// if the path was malformed or does not name its chunk.
     pub fn parse_from_sqpath<P: AsRef<SqPath>>(sqpath: P) -> Option<SqPackNumber> {
-        let sqpath = sqpath.as_ref();
-        let s = sqpath.as_str();
+        sqpath.as_ref().parse().ok()?.chunk
+    }
 
-        s.split('/')
-            .skip(2)
-            .next()
-            .and_then(|filename_str: &str| filename_str.split('_').next())
-            .and_then(|part: &str| {
-                let val = u8::from_str_radix(part, 16).ok().unwrap_or(0);
-                Some(SqPackNumber(val))
-            })
+    /// Finds the chunk of a path in `file_type` and `expansion` from its third folder segment, if
+    /// it has one. See `EXPANSION_CHUNK_RULES`.
+    fn parse(
+        file_type: FileType,
+        expansion: Expansion,
+        segment: Option<&str>,
+    ) -> Option<SqPackNumber> {
+        let rule = match expansion {
+            Expansion::FFXIV => ChunkRule::Single,
+            _ => EXPANSION_CHUNK_RULES
+                .iter()
+                .find(|(ft, _)| *ft == file_type)
+                .map_or(ChunkRule::Single, |&(_, rule)| rule),
+        };
+        match rule {
+            ChunkRule::Single => Some(SqPackNumber(0)),
+            ChunkRule::ZonePrefix => {
+                let (prefix, _) = segment?.split_once('_')?;
+                if prefix.len() != 2 || !prefix.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return None;
+                }
+                u8::from_str_radix(prefix, 16).ok().map(SqPackNumber)
+            }
+        }
     }
 
+    /// Returns the numerical index of the dat/index file.
+    pub fn number(&self) -> u8 { self.0 }
+
     /// Returns the prefix for this numerical index as a byte array
     pub fn file_name_prefix_str(&self) -> [u8; 2] {
         // very simple byte to hex ascii chars implementation
@@ -409,7 +968,10 @@ impl Borrow<SqPath> for SqPathBuf {
 
 #[cfg(test)]
 mod sqpath_tests {
-    use crate::sqpath::{Expansion, FileType, SqPackNumber, SqPath, SqPathBuf};
+    use crate::{
+        error::SqPathError,
+        sqpath::{Expansion, FileType, Platform, SqPackNumber, SqPath, SqPathBuf},
+    };
     use std::borrow::Borrow;
 
     #[test]
@@ -419,6 +981,7 @@ mod sqpath_tests {
     }
 
     #[test]
+    #[allow(clippy::useless_asref)]
     fn sqpath_as_refs() {
         let a: &SqPath = "uwu".as_ref();
         let b: &SqPath = a.as_ref();
@@ -460,6 +1023,15 @@ mod sqpath_tests {
         assert_eq!(sq_index_path.file_hash, 0xE3B71579);
     }
 
+    #[test]
+    fn sq_index2_hash() {
+        assert_eq!(
+            SqPath::new("music/ffxiv/BGM_System_Title.scd").sq_index2_hash(),
+            0xE09DBB74
+        );
+        assert_eq!(SqPath::new("exd/root.exl").sq_index2_hash(), 0x3E16266C);
+    }
+
     #[test]
     fn to_owned_and_borrow() {
         let sqpath = SqPath::new("uwu");
@@ -470,6 +1042,64 @@ mod sqpath_tests {
         assert_eq!(x.inner, a.inner);
     }
 
+    #[test]
+    fn path_components() {
+        let path = SqPath::new("chara/equipment/e0001/model/c0201e0001_top.mdl");
+        assert_eq!(
+            path.parent(),
+            Some(SqPath::new("chara/equipment/e0001/model"))
+        );
+        assert_eq!(path.file_name(), Some("c0201e0001_top.mdl"));
+        assert_eq!(path.file_stem(), Some("c0201e0001_top"));
+        assert_eq!(path.extension(), Some("mdl"));
+        assert_eq!(path.components().count(), 5);
+        assert_eq!(path.components().next_back(), Some("c0201e0001_top.mdl"));
+
+        let dotfile = SqPath::new("exd/.hidden");
+        assert_eq!(dotfile.file_stem(), Some(".hidden"));
+        assert_eq!(dotfile.extension(), None);
+        let archive = SqPath::new("a/b.tar.gz");
+        assert_eq!(archive.file_stem(), Some("b.tar"));
+        assert_eq!(archive.extension(), Some("gz"));
+        assert_eq!(SqPath::new("music/").file_name(), None);
+        assert_eq!(SqPath::new("").file_name(), None);
+        assert_eq!(SqPath::new("root.exl").parent(), None);
+        assert_eq!(SqPath::new("root.exl").file_name(), Some("root.exl"));
+        assert_eq!(
+            SqPath::new("/a//b/").components().collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+    }
+
+    #[test]
+    fn path_building() {
+        assert_eq!(SqPath::new("").join("exd").as_str(), "exd");
+        assert_eq!(
+            SqPath::new("exd/").join("root.exl").as_str(),
+            "exd/root.exl"
+        );
+
+        let mut path = SqPathBuf::from(String::from("ui/icon"));
+        path.push("000000");
+        path.push("000001.tex");
+        assert_eq!(path.as_str(), "ui/icon/000000/000001.tex");
+        assert!(path.set_extension("png"));
+        assert_eq!(path.to_string(), "ui/icon/000000/000001.png");
+        assert!(path.set_extension(""));
+        assert_eq!(path.as_str(), "ui/icon/000000/000001");
+        assert!(path.set_extension("tex"));
+        assert!(path.pop());
+        assert_eq!(path.as_str(), "ui/icon/000000");
+        assert!(path.pop());
+        assert!(path.pop());
+        assert!(!path.pop());
+        assert_eq!(path, SqPathBuf::from("ui"));
+
+        let mut folder = SqPathBuf::new("music/");
+        assert!(!folder.set_extension("scd"));
+        assert_eq!(format!("{}", SqPath::new("music/ffxiv")), "music/ffxiv");
+    }
+
     #[test]
     fn file_type_parse() {
         let sqpath = SqPath::new("music/ffxiv/BGM_System_Title.scd");
@@ -634,6 +1264,44 @@ mod sqpath_tests {
         assert_eq!(Expansion::Heavensward.file_name_prefix_str(), "01");
         assert_eq!(Expansion::Stormblood.file_name_prefix_str(), "02");
         assert_eq!(Expansion::Shadowbringers.file_name_prefix_str(), "03");
+        assert_eq!(Expansion::Endwalker.file_name_prefix_str(), "04");
+        assert_eq!(Expansion::Dawntrail.file_name_prefix_str(), "05");
+        assert_eq!(Expansion::from_number(6).file_name_prefix_str(), "06");
+        assert_eq!(Expansion::from_number(26).file_name_prefix_str(), "1a");
+    }
+
+    #[test]
+    fn expansion_numbers() {
+        for n in 0..=255 {
+            assert_eq!(Expansion::from_number(n).number(), n);
+        }
+        assert_eq!(Expansion::from_number(5), Expansion::Dawntrail);
+        assert!(matches!(Expansion::from_number(6), Expansion::ExN(n) if n.number() == 6));
+        assert_eq!(Expansion::from_number(0).as_str(), "ffxiv");
+        assert_eq!(Expansion::Dawntrail.as_str(), "ex5");
+        assert_eq!(Expansion::from_number(6).as_str(), "ex6");
+        assert_eq!(Expansion::from_number(42).as_str(), "ex42");
+        assert_eq!(Expansion::from_number(255).as_str(), "ex255");
+    }
+
+    #[test]
+    fn expansion_parse_future() {
+        assert_eq!(
+            Expansion::parse_from_sqpath("bg/ex5/01_xkt_x6/twn/x6t1/level/bg.lgb"),
+            Some(Expansion::Dawntrail)
+        );
+        assert_eq!(
+            Expansion::parse_from_sqpath("music/ex6/bgm_ex6_title.scd"),
+            Some(Expansion::from_number(6))
+        );
+        assert_eq!(
+            Expansion::parse_from_sqpath("music/ex12/bgm_ex12_title.scd"),
+            Some(Expansion::from_number(12))
+        );
+        assert_eq!(Expansion::parse_from_sqpath("music/ex0/a.scd"), None);
+        assert_eq!(Expansion::parse_from_sqpath("music/ex05/a.scd"), None);
+        assert_eq!(Expansion::parse_from_sqpath("music/ex256/a.scd"), None);
+        assert_eq!(Expansion::parse_from_sqpath("music/exd/a.scd"), None);
     }
 
     #[test]
@@ -676,6 +1344,9 @@ mod sqpath_tests {
         assert_eq!(Expansion::Heavensward.file_name_prefix(), 0x01u8);
         assert_eq!(Expansion::Stormblood.file_name_prefix(), 0x02u8);
         assert_eq!(Expansion::Shadowbringers.file_name_prefix(), 0x03u8);
+        assert_eq!(Expansion::Endwalker.file_name_prefix(), 0x04u8);
+        assert_eq!(Expansion::Dawntrail.file_name_prefix(), 0x05u8);
+        assert_eq!(Expansion::from_number(7).file_name_prefix(), 0x07u8);
     }
 
     #[test]
@@ -690,14 +1361,48 @@ mod sqpath_tests {
             SqPackNumber::parse_from_sqpath("common/ex2/001_sdfsfda.adasd")
                 .unwrap()
                 .0,
-            1
+            0
         );
         assert_eq!(
             SqPackNumber::parse_from_sqpath("common/ex2/00b_sdfsfda.adasd")
                 .unwrap()
                 .0,
+            0
+        );
+        assert_eq!(
+            SqPackNumber::parse_from_sqpath("bg/ex2/0b_sdfsfda/level/bg.lgb")
+                .unwrap()
+                .0,
             11
         );
+        assert_eq!(
+            SqPackNumber::parse_from_sqpath("bg/ex1/01_abr_a2/twn/a2t1/level/bg.lgb"),
+            Some(SqPackNumber(1))
+        );
+        assert_eq!(
+            SqPackNumber::parse_from_sqpath("bg/ffxiv/sea_s1/twn/s1t1/level/bg.lgb"),
+            Some(SqPackNumber(0))
+        );
+        assert_eq!(
+            SqPackNumber::parse_from_sqpath("music/ex2/BGM_EX2_Ban_01.scd"),
+            Some(SqPackNumber(0))
+        );
+        assert_eq!(
+            SqPackNumber::parse_from_sqpath("cut/ex3/ab_ffxiv_ex3/ab01.cutb"),
+            Some(SqPackNumber(0))
+        );
+        assert_eq!(
+            SqPackNumber::parse_from_sqpath("bg/ex3/shared/level/bg.lgb"),
+            None
+        );
+        assert_eq!(
+            SqPackNumber::parse_from_sqpath("bg/ex3/+1_shared/level/bg.lgb"),
+            None
+        );
+        assert_eq!(
+            SqPackNumber::parse_from_sqpath("bg/ex3/001_shared/level/bg.lgb"),
+            None
+        );
     }
 
     #[test]
@@ -724,6 +1429,99 @@ mod sqpath_tests {
         );
     }
 
+    #[test]
+    fn parse_sqpath() {
+        let sqpath = SqPath::new("music/ex3/BGM_EX3_Event_05.scd");
+        let parsed = sqpath.parse().unwrap();
+        assert_eq!(parsed.file_type, FileType::Music);
+        assert_eq!(parsed.expansion, Expansion::Shadowbringers);
+        assert_eq!(parsed.chunk, Some(SqPackNumber(0)));
+        assert_eq!(parsed.folder, "music/ex3");
+        assert_eq!(parsed.file_name, "BGM_EX3_Event_05.scd");
+        assert_eq!(parsed.sq_index_hash(), sqpath.sq_index_hash().unwrap());
+        assert_eq!(
+            parsed.index_file_name(Platform::PS4).unwrap(),
+            "0c0300.ps4.index"
+        );
+    }
+
+    #[test]
+    fn parse_sqpath_errors() {
+        let error = |sqpath| SqPath::new(sqpath).parse().unwrap_err();
+        assert_eq!(error("music"), SqPathError::NoFolder);
+        assert_eq!(error("music/ffxiv/"), SqPathError::NoFileName);
+        assert_eq!(
+            error("musik/ffxiv/BGM_System_Title.scd"),
+            SqPathError::UnknownCategory("musik".into())
+        );
+        assert_eq!(
+            SqPath::new("bg/ex1/level/bg.lgb").sqpack_index_path("/home/uwu/ffxiv/sqpack"),
+            Err(SqPathError::NoChunk)
+        );
+        assert_eq!(
+            SqPath::new("music").sqpack_index_path("/home/uwu/ffxiv/sqpack"),
+            Err(SqPathError::NoFolder)
+        );
+    }
+
+    #[test]
+    fn normalized_paths() {
+        let cases = [
+            (
+                "music/ffxiv/bgm_system_title.scd",
+                "music/ffxiv/bgm_system_title.scd",
+            ),
+            (
+                "Music\\FFXIV\\BGM_System_Title.scd",
+                "music/ffxiv/bgm_system_title.scd",
+            ),
+            (
+                "/music/ffxiv/BGM_System_Title.scd",
+                "music/ffxiv/bgm_system_title.scd",
+            ),
+            ("  music//ffxiv/\\bgm.scd/\t", "music/ffxiv/bgm.scd"),
+            ("", ""),
+            ("\\/", ""),
+        ];
+        for (path, normalized) in cases {
+            let path = SqPathBuf::normalized(path);
+            assert_eq!(path.as_str(), normalized);
+            assert!(path.is_normalized());
+        }
+        assert!(!SqPath::new(" music/ffxiv/bgm.scd").is_normalized());
+        assert!(!SqPath::new("music/ffxiv/bgm.scd/").is_normalized());
+        assert!(!SqPath::new("music//ffxiv/bgm.scd").is_normalized());
+
+        let sqpath = SqPathBuf::normalized("\\Music\\FFXIV\\BGM_System_Title.scd");
+        assert_eq!(sqpath.sq_index2_hash(), 0xE09DBB74);
+        assert_eq!(
+            sqpath.sq_index_hash(),
+            SqPath::new("music/ffxiv/BGM_System_Title.scd").sq_index_hash()
+        );
+    }
+
+    #[test]
+    fn parse_ignores_case() {
+        let parsed = SqPath::new("MUSIC/Ex3/BGM_EX3_Event_05.scd")
+            .parse()
+            .unwrap();
+        assert_eq!(parsed.file_type, FileType::Music);
+        assert_eq!(parsed.expansion, Expansion::Shadowbringers);
+        assert_eq!(
+            FileType::parse_from_sqpath("Chara/equipment/e0001/model/c0201e0001_top.mdl"),
+            Some(FileType::Chara)
+        );
+        assert_eq!(
+            Expansion::parse_from_sqpath("bg/FFXIV/sea_s1/twn/s1t1/level/bg.lgb"),
+            Some(Expansion::FFXIV)
+        );
+        assert_eq!(
+            Expansion::parse_from_sqpath("bg/EX5/01_xkt_x6/twn/x6t1/level/bg.lgb"),
+            Some(Expansion::Dawntrail)
+        );
+        assert_eq!(Expansion::parse_from_sqpath("music/é1/a.scd"), None);
+    }
+
     #[test]
     fn sqpack_index_path() {
         let index = SqPath::new("music/ffxiv/BGM_System_Title.scd")
@@ -747,7 +1545,153 @@ mod sqpath_tests {
                 .sqpack_index_path(path)
                 .unwrap()
                 .as_os_str(),
-            "/home/uwu/ffxiv/sqpack/ex2/0002fe.win32.index"
+            "/home/uwu/ffxiv/sqpack/ex2/000200.win32.index"
+        );
+        assert_eq!(
+            SqPath::new("bg/ex2/03_fld_r2/level/bg.lgb")
+                .sqpack_index_path(path)
+                .unwrap()
+                .as_os_str(),
+            "/home/uwu/ffxiv/sqpack/ex2/020203.win32.index"
+        );
+        assert_eq!(
+            SqPath::new("bg/ex2/shared/level/bg.lgb").sqpack_index_path(path),
+            Err(SqPathError::NoChunk)
+        );
+        assert_eq!(
+            SqPath::new("music/ex5/BGM_EX5_Field_01.scd")
+                .sqpack_index_path(path)
+                .unwrap()
+                .as_os_str(),
+            "/home/uwu/ffxiv/sqpack/ex5/0c0500.win32.index"
+        );
+        assert_eq!(
+            SqPath::new("music/ex6/BGM_EX6_Field_01.scd")
+                .sqpack_index_path(path)
+                .unwrap()
+                .as_os_str(),
+            "/home/uwu/ffxiv/sqpack/ex6/0c0600.win32.index"
+        );
+    }
+
+    #[test]
+    fn sqpack_index_path_categories() {
+        let cases = [
+            ("common/font/AXIS_12.fdt", "ffxiv/000000"),
+            (
+                "bgcommon/hou/indoor/general/0001/bgparts/fun_b0_m0001.mdl",
+                "ffxiv/010000",
+            ),
+            ("bg/ffxiv/sea_s1/twn/s1t1/level/bg.lgb", "ffxiv/020000"),
+            ("bg/ex1/01_abr_a2/fld/a2f1/level/bg.lgb", "ex1/020101"),
+            ("bg/ex4/05_zon_z5/fld/z5f1/level/bg.lgb", "ex4/020405"),
+            (
+                "cut/ffxiv/sound/manfst/manfst00000/vo_manfst00000_000010_m_en.scd",
+                "ffxiv/030000",
+            ),
+            ("cut/ex3/movie/voice/ex3_001.scd", "ex3/030300"),
+            (
+                "chara/equipment/e0001/model/c0201e0001_top.mdl",
+                "ffxiv/040000",
+            ),
+            ("shader/sm5/shpk/character.shpk", "ffxiv/050000"),
+            ("ui/icon/000000/000001_hr1.tex", "ffxiv/060000"),
+            ("sound/battle/mon/9055.scd", "ffxiv/070000"),
+            ("vfx/common/eff/dk05th_stdn0t.avfx", "ffxiv/080000"),
+            ("ui_script/system/common.luab", "ffxiv/090000"),
+            ("exd/root.exl", "ffxiv/0a0000"),
+            ("game_script/system/mainmenu.luab", "ffxiv/0b0000"),
+            ("music/ex4/BGM_EX4_Field_Ult_Day01.scd", "ex4/0c0400"),
+        ];
+        for (sqpath, index) in cases {
+            assert_eq!(
+                SqPath::new(sqpath).sqpack_index_path("sqpack").unwrap(),
+                std::path::Path::new("sqpack").join(format!("{}.win32.index", index)),
+                "{}",
+                sqpath
+            );
+        }
+    }
+
+    #[test]
+    fn chunks_of_known_paths() {
+        // one path of each category that expansions store, checked against the indexes they ship in
+        let cases = [
+            ("bg/ex1/01_abr_a2/fld/a2f1/level/bg.lgb", "ex1/020101"),
+            ("bg/ex2/02_dom_d1/fld/d1f1/level/bg.lgb", "ex2/020202"),
+            ("bg/ex3/01_nvt_n4/fld/n4f1/level/bg.lgb", "ex3/020301"),
+            (
+                "bg/ex3/00_default/common/bgparts/n4_a0_gra01.mdl",
+                "ex3/020300",
+            ),
+            ("bg/ex5/0a_xkt_x6/fld/x6f1/level/bg.lgb", "ex5/02050a"),
+            (
+                "cut/ex1/sound/voicem/voiceman_02000/vo_voiceman_02000_000010_m_en.scd",
+                "ex1/030100",
+            ),
+            ("cut/ex2/01_sea_s1/anim/cut.tmb", "ex2/030200"),
+            ("music/ex2/BGM_EX2_System_Title.scd", "ex2/0c0200"),
+            ("music/ex3/01_event/BGM_EX3_Event_05.scd", "ex3/0c0300"),
+            ("bg/ffxiv/01_wil_w1/twn/w1t1/level/bg.lgb", "ffxiv/020000"),
+        ];
+        for (sqpath, index) in cases {
+            assert_eq!(
+                SqPath::new(sqpath).sqpack_index_path("sqpack").unwrap(),
+                std::path::Path::new("sqpack").join(format!("{}.win32.index", index)),
+                "{}",
+                sqpath
+            );
+        }
+
+        // expansion levels outside of a zone folder may be in any chunk
+        for sqpath in [
+            "bg/ex1/01_abr_a2.lgb",
+            "bg/ex2/shared/level/bg.lgb",
+            "bg/ex4/1_zon/level/bg.lgb",
+            "bg/ex4/zz_zon/level/bg.lgb",
+        ] {
+            assert_eq!(
+                SqPath::new(sqpath).parse().unwrap().chunk,
+                None,
+                "{}",
+                sqpath
+            );
+        }
+    }
+
+    #[test]
+    fn sqpack_index_path_platforms() {
+        let sqpath = SqPath::new("music/ex3/BGM_EX3_Event_05.scd");
+        assert_eq!(
+            sqpath
+                .sqpack_index_path_for("/home/uwu/ffxiv/sqpack", Platform::PS3)
+                .unwrap()
+                .as_os_str(),
+            "/home/uwu/ffxiv/sqpack/ex3/0c0300.ps3.index"
+        );
+        assert_eq!(
+            sqpath
+                .sqpack_index2_path_for("/home/uwu/ffxiv/sqpack", Platform::PS5)
+                .unwrap()
+                .as_os_str(),
+            "/home/uwu/ffxiv/sqpack/ex3/0c0300.ps5.index2"
+        );
+        for id in 0..4 {
+            assert_eq!(Platform::from_id(id).unwrap().id(), id);
+        }
+        assert_eq!(Platform::from_id(4), None);
+        assert!(Platform::PS3.is_big_endian());
+        assert!(!Platform::PS4.is_big_endian());
+    }
+
+    #[test]
+    fn sqpack_index2_path() {
+        assert_eq!(
+            SqPath::new("music/ex3/BGM_EX3_Event_05.scd")
+                .sqpack_index2_path("/home/uwu/ffxiv/sqpack")
+                .unwrap()
+                .as_os_str(),
+            "/home/uwu/ffxiv/sqpack/ex3/0c0300.win32.index2"
         );
     }
 }
@@ -0,0 +1,53 @@
+use crate::io::writer::SqPackWriter;
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A SqPack directory in the temp dir, deleted again when dropped
+pub(crate) struct TempSqPack {
+    root: PathBuf,
+}
+
+impl TempSqPack {
+    /// Creates an empty directory named after `name`. Every directory gets its own number, and
+    /// numbers already taken, such as by a run that crashed, are skipped.
+    pub(crate) fn new(name: &str) -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        loop {
+            let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+            let root =
+                std::env::temp_dir().join(format!("sqpack-{}-{}-{}", name, std::process::id(), id));
+            match fs::create_dir(&root) {
+                Ok(()) => return Self { root },
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+                Err(err) => panic!("creating {}: {}", root.display(), err),
+            }
+        }
+    }
+
+    pub(crate) fn root(&self) -> &Path { &self.root }
+
+    /// Writes `files` into the index at `index_path`, relative to the root, and returns the full
+    /// path of the index
+    pub(crate) fn write<P: AsRef<str>, D: AsRef<[u8]>>(
+        &self,
+        index_path: &str,
+        files: &[(P, D)],
+    ) -> PathBuf {
+        let index_path = self.root.join(index_path);
+        fs::create_dir_all(index_path.parent().unwrap()).unwrap();
+        let mut writer = SqPackWriter::new();
+        for (path, data) in files {
+            writer.add(path.as_ref(), data.as_ref()).unwrap();
+        }
+        writer.write_files(&index_path).unwrap();
+        index_path
+    }
+}
+
+impl Drop for TempSqPack {
+    fn drop(&mut self) { let _ = fs::remove_dir_all(&self.root); }
+}
@@ -2,15 +2,10 @@ extern crate md5;
 extern crate sqpack;
 extern crate walkdir;
 
-const FFXIV_SQPACK_PATH: &'static str = "FFXIV_SQPACK_PATH";
+const FFXIV_SQPACK_PATH: &str = "FFXIV_SQPACK_PATH";
 
-use sqpack::{io::index::IndexReader, SqPath};
-use std::{
-    collections::HashMap,
-    env,
-    fs::{File, FileType},
-    io::{BufRead, BufReader, Read},
-};
+use sqpack::io::index::IndexReader;
+use std::{collections::HashMap, env, fs::File, io::Read};
 
 fn get_env_vars() -> HashMap<String, String> { env::vars().collect() }
 
@@ -27,8 +22,7 @@ fn index_reader_iterators() {
     walkdir::WalkDir::new(path)
         .follow_links(true)
         .into_iter()
-        .filter(|entry_res| entry_res.is_ok())
-        .map(|entry_res| entry_res.unwrap())
+        .flatten()
         .filter(|entry| entry.file_type().is_file())
         .filter(|entry| {
             entry
@@ -103,3 +97,245 @@ fn read_file() {
     let digest = md5::compute(data).0;
     assert_eq!(expected, digest, "File not equal to expected file!")
 }
+
+#[test]
+fn sqpack_read_file() {
+    use sqpack::SqPack;
+
+    let sqpack = SqPack::new(&get_env_vars()[FFXIV_SQPACK_PATH]);
+    let sqpath = "music/ffxiv/BGM_System_Title.scd";
+    assert!(sqpack.exists(sqpath).expect("Checking file"));
+    assert!(!sqpack
+        .exists("music/ffxiv/BGM_Does_Not_Exist.scd")
+        .expect("Checking missing file"));
+
+    let data = sqpack.read_to_vec(sqpath).expect("Reading");
+    let expected: [u8; 16] = [
+        0x43, 0x51, 0x52, 0x41, 0xA8, 0xE7, 0x8E, 0xCC, 0xD5, 0xE1, 0xB3, 0x3A, 0xBE, 0x89, 0xDB,
+        0xCC,
+    ];
+    let digest = md5::compute(data).0;
+    assert_eq!(expected, digest, "File not equal to expected file!")
+}
+
+#[test]
+fn index2_matches_index() {
+    use sqpack::{io::index::Index2Reader, SqPath};
+
+    let sqpack = &get_env_vars()[FFXIV_SQPACK_PATH];
+    let sqpath = SqPath::new("music/ffxiv/BGM_System_Title.scd");
+
+    let index_path = sqpath.sqpack_index_path(sqpack).unwrap();
+    let mut reader = IndexReader::new(File::open(index_path).unwrap()).unwrap();
+    let index_entry = reader
+        .files()
+        .expect("Failed to create files iterator")
+        .map(|res| res.expect("Failed to read file"))
+        .find(|entry| Some(entry.path_hash) == sqpath.sq_index_hash())
+        .expect("File not in index");
+
+    let index2_path = sqpath.sqpack_index2_path(sqpack).unwrap();
+    let mut reader2 = Index2Reader::new(File::open(index2_path).unwrap()).unwrap();
+    let index2_entry = reader2
+        .find(sqpath.sq_index2_hash())
+        .expect("Failed to read index2")
+        .expect("File not in index2");
+
+    assert_eq!(index_entry.data_offset, index2_entry.data_offset);
+    assert_eq!(index_entry.dat_file, index2_entry.dat_file);
+}
+
+#[test]
+fn index_cache_lookups() {
+    use sqpack::{
+        io::{dat::SqFile, index::IndexCache},
+        SqPath,
+    };
+
+    let sqpack = &get_env_vars()[FFXIV_SQPACK_PATH];
+    let sqpath = SqPath::new("music/ffxiv/BGM_System_Title.scd");
+    let index_path = sqpath.sqpack_index_path(sqpack).unwrap();
+    let mut reader = IndexReader::new(File::open(index_path).unwrap()).unwrap();
+    let cache = IndexCache::from_reader(&mut reader).expect("Caching index");
+
+    assert_eq!(cache.len(), reader.files_count().unwrap());
+    assert_eq!(cache.folders().count(), reader.folders_count().unwrap());
+    assert_eq!(cache.files().count(), cache.len());
+    assert!(cache.contains(sqpath));
+    assert!(!cache.contains("music/ffxiv/BGM_Does_Not_Exist.scd"));
+
+    let hash = sqpath.sq_index_hash().unwrap();
+    let entry = cache.get_hash(hash).expect("File not in cache");
+    assert_eq!(cache.get(sqpath), Some(entry));
+    let folder = cache.folder(hash.folder_hash).expect("Folder not in cache");
+    assert_eq!(folder.get(hash.file_hash), Some(entry));
+
+    let mut sqfile = SqFile::open_sqpath_cached(sqpath, sqpack, &cache).expect("Opening file");
+    let mut data = Vec::with_capacity(sqfile.total_size());
+    sqfile.read_to_end(&mut data).expect("Reading");
+    assert_eq!(data.len(), sqfile.total_size());
+}
+
+/// Opens the first file in the index `index_name` of the base game with the given content type
+fn open_first_of_type(
+    index_name: &str,
+    content_type: sqpack::io::dat::ContentType,
+) -> sqpack::io::dat::SqFile<File> {
+    use sqpack::io::dat::SqFile;
+
+    let index_path = std::path::Path::new(&get_env_vars()[FFXIV_SQPACK_PATH])
+        .join("ffxiv")
+        .join(index_name);
+    let mut reader = IndexReader::new(File::open(&index_path).unwrap()).unwrap();
+    let entries = reader
+        .files()
+        .expect("Failed to create files iterator")
+        .map(|res| res.expect("Failed to read file"))
+        .collect::<Vec<_>>();
+    entries
+        .into_iter()
+        .filter_map(|entry| SqFile::open_entry(&index_path, entry).ok())
+        .find(|file| file.content_type() == content_type)
+        .expect("No file of the content type in the index")
+}
+
+#[test]
+fn read_model() {
+    use sqpack::{io::dat::ContentType, SqPack};
+
+    let sqpack = SqPack::new(&get_env_vars()[FFXIV_SQPACK_PATH]);
+    let mut file = sqpack
+        .open("chara/human/c0101/obj/body/b0001/model/c0101b0001_top.mdl")
+        .expect("Opening model");
+    assert_eq!(file.content_type(), ContentType::Model);
+    let mut data = Vec::with_capacity(file.total_size());
+    file.read_to_end(&mut data).expect("Reading");
+    assert_eq!(data.len(), file.total_size());
+
+    // the stack and runtime sizes in the rebuilt header must fit within the file
+    let stack_size = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+    let runtime_size = u32::from_le_bytes(data[8..12].try_into().unwrap()) as usize;
+    assert!(0x44 + stack_size + runtime_size <= data.len());
+}
+
+#[test]
+fn read_texture() {
+    use sqpack::io::dat::ContentType;
+
+    let mut file = open_first_of_type("060000.win32.index", ContentType::Texture);
+    let mut data = Vec::with_capacity(file.total_size());
+    file.read_to_end(&mut data).expect("Reading");
+    assert_eq!(data.len(), file.total_size());
+
+    // the first mipmap offset in the .tex header points just past the header
+    let first_mip = u32::from_le_bytes(data[0x1C..0x20].try_into().unwrap()) as usize;
+    assert!(first_mip <= data.len());
+}
+
+#[test]
+fn seek_file() {
+    use sqpack::io::dat::SqFile;
+    use std::io::{Seek, SeekFrom};
+
+    let sqpack = &get_env_vars()[FFXIV_SQPACK_PATH];
+    let sqpath = "music/ffxiv/BGM_System_Title.scd";
+    let mut sqfile = SqFile::open_sqpath(sqpath, sqpack).expect("Opening file");
+    let mut data = Vec::with_capacity(sqfile.total_size());
+    sqfile.read_to_end(&mut data).expect("Reading");
+
+    for offset in [0, 1, 15999, 16000, 16001, data.len() / 2, data.len() - 1] {
+        sqfile
+            .seek(SeekFrom::Start(offset as u64))
+            .expect("Seeking");
+        let mut rest = Vec::new();
+        sqfile.read_to_end(&mut rest).expect("Reading after seek");
+        assert_eq!(&data[offset..], rest.as_slice());
+    }
+}
+
+#[test]
+fn sqpack_headers() {
+    use sqpack::io::{dat::DatReader, header::SqPackFileType};
+    use std::path::Path;
+
+    let sqpack = Path::new(&get_env_vars()[FFXIV_SQPACK_PATH]).join("ffxiv");
+    let reader = IndexReader::new(File::open(sqpack.join("0c0000.win32.index")).unwrap())
+        .expect("Opening index");
+    assert_eq!(reader.header().file_type, SqPackFileType::Index);
+    assert_eq!(reader.header().header_size, 0x400);
+    assert_eq!(reader.platform(), sqpack::sqpath::Platform::Win32);
+
+    let reader =
+        DatReader::new(File::open(sqpack.join("0c0000.win32.dat0")).unwrap()).expect("Opening dat");
+    assert_eq!(reader.header().file_type, SqPackFileType::Dat);
+    assert_eq!(reader.header().header_size, 0x400);
+}
+
+#[test]
+fn index_segments() {
+    use sqpack::io::index::SegmentKind;
+    use std::path::Path;
+
+    let sqpack = Path::new(&get_env_vars()[FFXIV_SQPACK_PATH]).join("ffxiv");
+    let index = File::open(sqpack.join("0a0000.win32.index")).unwrap();
+    let mut reader = IndexReader::new(index).expect("Opening index");
+    assert!(reader.dat_count().expect("Reading dat count") >= 1);
+
+    let segments = reader.segments().expect("Reading segments");
+    let files = segments[0];
+    assert_eq!(files.kind, SegmentKind::Files);
+    assert_eq!(files.size as usize >> 4, reader.files_count().unwrap());
+    for synonym in reader.synonyms().expect("Reading synonyms") {
+        synonym.expect("Reading synonym entry");
+    }
+}
+
+#[test]
+fn verify_index() {
+    use std::path::Path;
+
+    let sqpack = Path::new(&get_env_vars()[FFXIV_SQPACK_PATH]).join("ffxiv");
+    let index = File::open(sqpack.join("0a0000.win32.index")).unwrap();
+    let mut reader = IndexReader::new(index).expect("Opening index");
+    let report = reader.verify().expect("Verifying index");
+    assert!(report.is_ok(), "{:?}", report.mismatches);
+}
+
+#[test]
+fn resolve_chunks() {
+    use sqpack::{SqPack, SqPath};
+
+    let root = &get_env_vars()[FFXIV_SQPACK_PATH];
+    let sqpack = SqPack::new(root);
+    let paths = [
+        ("common/font/AXIS_12.fdt", "ffxiv/000000.win32.index"),
+        (
+            "bg/ffxiv/sea_s1/twn/s1t1/level/bg.lgb",
+            "ffxiv/020000.win32.index",
+        ),
+        (
+            "bg/ex1/01_abr_a2/fld/a2f1/level/bg.lgb",
+            "ex1/020101.win32.index",
+        ),
+        (
+            "chara/human/c0101/obj/body/b0001/model/c0101b0001_top.mdl",
+            "ffxiv/040000.win32.index",
+        ),
+        ("shader/sm5/shpk/character.shpk", "ffxiv/050000.win32.index"),
+        ("exd/root.exl", "ffxiv/0a0000.win32.index"),
+        (
+            "music/ffxiv/BGM_System_Title.scd",
+            "ffxiv/0c0000.win32.index",
+        ),
+    ];
+    for (sqpath, index) in paths {
+        let index_path = SqPath::new(sqpath).sqpack_index_path(root).unwrap();
+        assert_eq!(
+            index_path,
+            std::path::Path::new(root).join(index),
+            "{}",
+            sqpath
+        );
+        assert!(sqpack.exists(sqpath).expect("Checking file"), "{}", sqpath);
+    }
+}